@@ -0,0 +1,85 @@
+//! Throughput of `linebreaks` and `break_properties_into`, compared to looking up the class of
+//! every character in the trie, as done before ASCII text got a fast path.
+//!
+//! Run with `cargo bench`, adding `--features simd` to classify runs of ASCII characters with SIMD
+//! instructions where supported.
+
+use std::time::{Duration, Instant};
+use unicode_linebreak::{
+    break_properties_into, break_property, linebreaks, BreakClass, BreakOpportunity, LineBreakState,
+};
+
+const ASCII: &str = "The quick brown fox jumps over the lazy dog. \"Hello, world!\" (1-2) 3.14\n";
+const NON_ASCII: &str = "Съешь же ещё этих мягких французских булок. いろはにほへと ちりぬるを\n";
+
+/// Returns the throughput in MiB/s of the fastest of a number of runs of each function.
+///
+/// Runs are interleaved so that both functions are equally affected by noise.
+fn bench<const N: usize>(s: &str, fs: [&dyn Fn(&str) -> usize; N]) -> [f64; N] {
+    let mut best = [Duration::MAX; N];
+    for _ in 0..100 {
+        for (f, best) in fs.iter().zip(&mut best) {
+            let start = Instant::now();
+            assert_ne!(f(s), 0);
+            *best = start.elapsed().min(*best);
+        }
+    }
+    best.map(|x| s.len() as f64 / x.as_secs_f64() / (1024.0 * 1024.0))
+}
+
+/// Returns break opportunities the way `linebreaks` did without the ASCII fast path.
+fn linebreaks_trie(s: &str) -> impl Iterator<Item = (usize, BreakOpportunity)> + '_ {
+    let mut state = LineBreakState::new();
+    s.char_indices()
+        .filter_map(move |(i, c)| state.step(break_property(c as u32)).map(|x| (i, x)))
+        .chain(Some((s.len(), BreakOpportunity::Mandatory)))
+}
+
+fn bench_linebreaks(name: &str, s: &str) {
+    // Sum the indices and mandatory breaks, with a loop as callers typically would
+    let sum_breaks = |s: &str| {
+        let mut sum = 0;
+        for (i, x) in linebreaks(s) {
+            sum += i + (x == BreakOpportunity::Mandatory) as usize;
+        }
+        sum
+    };
+    let sum_breaks_trie = |s: &str| {
+        let mut sum = 0;
+        for (i, x) in linebreaks_trie(s) {
+            sum += i + (x == BreakOpportunity::Mandatory) as usize;
+        }
+        sum
+    };
+    assert_eq!(sum_breaks(s), sum_breaks_trie(s));
+    let [fast, trie] = bench(s, [&sum_breaks, &sum_breaks_trie]);
+    println!(
+        "{:<10} linebreaks:            {:>7.1} MiB/s, trie only: {:>7.1} MiB/s",
+        name, fast, trie
+    );
+}
+
+fn bench_break_properties(name: &str, s: &str) {
+    let out = std::cell::RefCell::new(vec![BreakClass::Unknown; s.len()]);
+    let classify = |s: &str| break_properties_into(s, &mut out.borrow_mut());
+    let classify_trie = |s: &str| {
+        let mut out = out.borrow_mut();
+        out.iter_mut()
+            .zip(s.chars())
+            .map(|(x, c)| *x = break_property(c as u32))
+            .count()
+    };
+    let [fast, trie] = bench(s, [&classify, &classify_trie]);
+    println!(
+        "{:<10} break_properties_into: {:>7.1} MiB/s, trie only: {:>7.1} MiB/s",
+        name, fast, trie
+    );
+}
+
+fn main() {
+    for (name, text) in [("ascii", ASCII), ("non-ascii", NON_ASCII)] {
+        let s = text.repeat(16 * 1024);
+        bench_linebreaks(name, &s);
+        bench_break_properties(name, &s);
+    }
+}
@@ -77,6 +77,67 @@ impl FromStr for BreakClass {
     }
 }
 
+impl FromStr for EastAsianWidth {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use EastAsianWidth::*;
+        Ok(match s {
+            "A" => Ambiguous,
+            "F" => Fullwidth,
+            "H" => Halfwidth,
+            "N" => Neutral,
+            "Na" => Narrow,
+            "W" => Wide,
+            _ => return Err("Invalid East Asian Width"),
+        })
+    }
+}
+
+static EAST_ASIAN_WIDTH_TABLE: [&str; 6] = ["A", "F", "H", "N", "Na", "W"];
+
+impl FromStr for GraphemeClusterBreak {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use GraphemeClusterBreak::*;
+        Ok(match s {
+            "CR" => CarriageReturn,
+            "LF" => LineFeed,
+            "Control" => Control,
+            "Extend" => Extend,
+            "ZWJ" => ZeroWidthJoiner,
+            "Regional_Indicator" => RegionalIndicator,
+            "Prepend" => Prepend,
+            "SpacingMark" => SpacingMark,
+            "L" => HangulLJamo,
+            "V" => HangulVJamo,
+            "T" => HangulTJamo,
+            "LV" => HangulLvSyllable,
+            "LVT" => HangulLvtSyllable,
+            _ => return Err("Invalid Grapheme_Cluster_Break"),
+        })
+    }
+}
+
+static GRAPHEME_CLUSTER_BREAK_TABLE: [&str; 15] = [
+    "Other",
+    "CarriageReturn",
+    "LineFeed",
+    "Control",
+    "Extend",
+    "ZeroWidthJoiner",
+    "RegionalIndicator",
+    "Prepend",
+    "SpacingMark",
+    "HangulLJamo",
+    "HangulVJamo",
+    "HangulTJamo",
+    "HangulLvSyllable",
+    "HangulLvtSyllable",
+    "ExtendedPictographic",
+];
+
 const NUM_CLASSES: usize = 43;
 static BREAK_CLASS_TABLE: [&str; NUM_CLASSES] = [
     "BK", "CR", "LF", "CM", "NL", "SG", "WJ", "ZW", "GL", "SP", "ZWJ", "B2", "BA", "BB", "HY",
@@ -280,7 +341,7 @@ impl<T: Copy + PartialEq + Eq + Hash> CpTrieBuilder<T> {
         if start >= end {
             return; // Empty range
         }
-        if end as usize > self.index.len() {
+        if end as usize > self.index.len() << SHIFT_3 {
             // Round up to CP_PER_INDEX_2_ENTRY boundary to simplify compaction
             let c = (end + CP_PER_INDEX_2_ENTRY - 1) & !(CP_PER_INDEX_2_ENTRY - 1);
             self.index.resize(
@@ -698,6 +759,28 @@ struct CpTrie<T> {
     data: Vec<T>,
 }
 
+/// Opens the data file of the specified version of the Unicode Character Database (UCD).
+///
+/// Files of a different version, as given in the first line, are rejected.
+fn open_ucd_file(
+    name: &str,
+    version: &str,
+) -> Result<impl Iterator<Item = String>, Box<dyn error::Error>> {
+    let path = format!("../{}.txt", name);
+    let file = File::open(&path).map_err(|e| {
+        format!(
+            "{}: {} (download it from https://www.unicode.org/Public/{}/ucd/)",
+            path, e, version
+        )
+    })?;
+    let mut lines = BufReader::new(file).lines().map(Result::unwrap);
+    let header = format!("# {}-{}.txt", name, version);
+    if lines.next().as_ref() != Some(&header) {
+        return Err(format!("{}: not from the Unicode {} UCD", path, version).into());
+    }
+    Ok(lines)
+}
+
 fn main() -> Result<(), Box<dyn error::Error>> {
     #[allow(clippy::assertions_on_constants)]
     const _: () = debug_assert!(NUM_STATES <= 0x3F, "too many states");
@@ -811,9 +894,17 @@ fn main() -> Result<(), Box<dyn error::Error>> {
 ;
 (?P<lb>\w{2,3}) # Line_Break property",
     )?;
-    let prop_ranges = BufReader::new(File::open("../LineBreak.txt")?)
+    let mut lines = BufReader::new(File::open("../LineBreak.txt")?)
         .lines()
-        .map(Result::unwrap)
+        .map(Result::unwrap);
+    // The other data files have to be of the same version
+    let version = lines
+        .next()
+        .as_deref()
+        .and_then(|l| l.strip_prefix("# LineBreak-")?.strip_suffix(".txt"))
+        .ok_or("../LineBreak.txt: missing version")?
+        .to_owned();
+    let prop_ranges = lines
         .filter(|l| !(l.starts_with('#') || l.is_empty()))
         .map(|l| {
             let caps = re.captures(&l).unwrap();
@@ -823,7 +914,16 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 .map_or(start, |m| u32::from_str_radix(m.as_str(), 16).unwrap());
             let lb: BreakClass = caps["lb"].parse().unwrap();
             (start..end + 1, lb)
-        });
+        })
+        .collect::<Vec<_>>();
+    // Classes of ASCII characters, for looking up without the trie
+    let ascii_classes = (0..0x80).map(|cp| {
+        prop_ranges
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&cp))
+            .map_or(XX, |&(_, lb)| lb)
+    });
     let trie = {
         // All code points, assigned and unassigned, that are not listed explicitly are given the value "XX"
         let mut builder = CpTrieBuilder::new(XX);
@@ -840,7 +940,93 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         // The unassigned code points in the following block default to "PR"
         builder.set_range(0x20A0..0x20CF + 1, PR);
 
-        prop_ranges.for_each(|(range, lb)| builder.set_range(range, lb));
+        prop_ranges
+            .iter()
+            .for_each(|(range, lb)| builder.set_range(range.clone(), *lb));
+        builder.build()
+    };
+
+    let re = Regex::new(
+        r"(?x)^
+(?P<start>[[:xdigit:]]{4,}) # Unicode code point
+(?:\.{2}(?P<end>[[:xdigit:]]{4,}))? # End of range
+\s*;\s*
+(?P<ea>\w{1,2}) # East_Asian_Width property",
+    )?;
+    let ea_ranges = open_ucd_file("EastAsianWidth", &version)?
+        .filter(|l| !(l.starts_with('#') || l.is_empty()))
+        .map(|l| {
+            let caps = re.captures(&l).unwrap();
+            let start = u32::from_str_radix(&caps["start"], 16).unwrap();
+            let end = caps
+                .name("end")
+                .map_or(start, |m| u32::from_str_radix(m.as_str(), 16).unwrap());
+            let ea: EastAsianWidth = caps["ea"].parse().unwrap();
+            (start..end + 1, ea)
+        });
+    let ea_trie = {
+        use EastAsianWidth::{Neutral, Wide};
+        // All code points, assigned or unassigned, that are not listed explicitly are given the value "N"
+        let mut builder = CpTrieBuilder::new(Neutral);
+        // The unassigned code points in the following blocks default to "W"
+        builder.set_range(0x3400..0x4DBF + 1, Wide);
+        builder.set_range(0x4E00..0x9FFF + 1, Wide);
+        builder.set_range(0xF900..0xFAFF + 1, Wide);
+        builder.set_range(0x20000..0x2FFFD + 1, Wide);
+        builder.set_range(0x30000..0x3FFFD + 1, Wide);
+
+        ea_ranges.for_each(|(range, ea)| builder.set_range(range, ea));
+        builder.build()
+    };
+
+    let re = Regex::new(
+        r"(?x)^
+(?P<start>[[:xdigit:]]{4,}) # Unicode code point
+(?:\.{2}(?P<end>[[:xdigit:]]{4,}))? # End of range
+\s*;\s*
+(?P<prop>\w+) # Property value or name",
+    )?;
+    let parse_range = |l: &str| {
+        let caps = re.captures(l).unwrap();
+        let start = u32::from_str_radix(&caps["start"], 16).unwrap();
+        let end = caps
+            .name("end")
+            .map_or(start, |m| u32::from_str_radix(m.as_str(), 16).unwrap());
+        (start..end + 1, caps["prop"].to_owned())
+    };
+    let gcb_ranges = open_ucd_file("GraphemeBreakProperty", &version)?
+        .filter(|l| !(l.starts_with('#') || l.is_empty()))
+        .map(|l| parse_range(&l))
+        .collect::<Vec<_>>();
+    // Only the UCD files carry their version in the first line
+    let file = File::open("../emoji-data.txt").map_err(|e| {
+        format!(
+            "../emoji-data.txt: {} (download it from https://www.unicode.org/Public/{}/ucd/emoji/)",
+            e, version
+        )
+    })?;
+    let pictographic_ranges = BufReader::new(file)
+        .lines()
+        .map(Result::unwrap)
+        .filter(|l| !(l.starts_with('#') || l.is_empty()))
+        .map(|l| parse_range(&l))
+        .filter(|(_, prop)| prop == "Extended_Pictographic");
+    let gcb_trie = {
+        use GraphemeClusterBreak::{ExtendedPictographic, Other};
+        // All code points that are not listed explicitly are given the value "Other"
+        let mut builder = CpTrieBuilder::new(Other);
+        for (range, gcb) in &gcb_ranges {
+            builder.set_range(range.clone(), gcb.parse()?);
+        }
+        for (range, _) in pictographic_ranges {
+            assert!(
+                gcb_ranges
+                    .iter()
+                    .all(|(r, _)| r.end <= range.start || range.end <= r.start),
+                "Extended_Pictographic characters are expected to be Other"
+            );
+            builder.set_range(range, ExtendedPictographic);
+        }
         builder.build()
     };
 
@@ -860,10 +1046,56 @@ static BREAK_PROP_TRIE_DATA: [BreakClass; {}] = [",
         .flat_map(|x| [BREAK_CLASS_TABLE[x as usize], ","])
         .try_for_each(|s| write!(stream, "{}", s))?;
     write!(
+        stream,
+        "];\n\nstatic ASCII_BREAK_PROP: [BreakClass; 128] = ["
+    )?;
+    ascii_classes
+        .flat_map(|x| [BREAK_CLASS_TABLE[x as usize], ","])
+        .try_for_each(|s| write!(stream, "{}", s))?;
+    writeln!(
         stream,
         "];
 
-static PAIR_TABLE: [[u8; {}]; {}] = [",
+const EAST_ASIAN_WIDTH_TRIE_HIGH_START: u32 = {};
+static EAST_ASIAN_WIDTH_TRIE_INDEX: [u16; {}] = {:?};
+static EAST_ASIAN_WIDTH_TRIE_DATA: [EastAsianWidth; {}] = {{
+    use EastAsianWidth::{{
+        Ambiguous as A, Fullwidth as F, Halfwidth as H, Narrow as Na, Neutral as N, Wide as W,
+    }};
+    [",
+        ea_trie.high_start,
+        ea_trie.index.len(),
+        ea_trie.index,
+        ea_trie.data.len(),
+    )?;
+    ea_trie
+        .data
+        .into_iter()
+        .flat_map(|x| [EAST_ASIAN_WIDTH_TABLE[x as usize], ","])
+        .try_for_each(|s| write!(stream, "{}", s))?;
+    writeln!(
+        stream,
+        "]
+}};
+
+const GRAPHEME_CLUSTER_BREAK_TRIE_HIGH_START: u32 = {};
+static GRAPHEME_CLUSTER_BREAK_TRIE_INDEX: [u16; {}] = {:?};
+static GRAPHEME_CLUSTER_BREAK_TRIE_DATA: [GraphemeClusterBreak; {}] = {{
+    use GraphemeClusterBreak::*;
+    [",
+        gcb_trie.high_start,
+        gcb_trie.index.len(),
+        gcb_trie.index,
+        gcb_trie.data.len(),
+    )?;
+    gcb_trie
+        .data
+        .into_iter()
+        .flat_map(|x| [GRAPHEME_CLUSTER_BREAK_TABLE[x as usize], ","])
+        .try_for_each(|s| write!(stream, "{}", s))?;
+    write!(
+        stream,
+        "]\n}};\n\nstatic PAIR_TABLE: [[u8; {}]; {}] = [",
         NUM_CLASSES_EOT, NUM_STATES
     )?;
     for row in &pair_table {
@@ -0,0 +1,302 @@
+//! Wrapping of terminal output containing ANSI escape sequences.
+//!
+//! Control Sequence Introducer (CSI) sequences, such as the Select Graphic Rendition (SGR)
+//! sequence `ESC[31m`, and Operating System Command (OSC) sequences are skipped when finding
+//! break opportunities and measuring columns, as if they were not there.
+
+use crate::{
+    column_width,
+    wrap::{trim_trailing_whitespace, wrap_greedy},
+};
+use alloc::{string::String, vec::Vec};
+
+const ESC: char = '\x1B';
+
+/// Returns the length of the escape sequence at the start of the string, if any.
+fn escape_len(s: &str) -> Option<usize> {
+    let mut bytes = s.bytes();
+    if bytes.next()? != ESC as u8 {
+        return None;
+    }
+    Some(match bytes.next() {
+        // CSI: Parameter and intermediate bytes followed by a final byte
+        Some(b'[') => bytes
+            .position(|b| (0x40..=0x7E).contains(&b))
+            .map_or(s.len(), |i| i + 3),
+        // OSC: Terminated by BEL or ST
+        Some(b']') => {
+            let mut prev = 0;
+            bytes
+                .position(|b| {
+                    let is_end = b == 0x07 || prev == ESC as u8 && b == b'\\';
+                    prev = b;
+                    is_end
+                })
+                .map_or(s.len(), |i| i + 3)
+        }
+        Some(b) if b.is_ascii() => 2,
+        _ => 1,
+    })
+}
+
+/// Returns the string with all escape sequences removed.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::ansi::strip_escapes;
+/// assert_eq!(strip_escapes("\x1B[1mbold\x1B[0m"), "bold");
+/// ```
+pub fn strip_escapes(s: &str) -> String {
+    let mut plain = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        if let Some(len) = escape_len(&s[i..]) {
+            i += len;
+        } else {
+            let c = s[i..].chars().next().unwrap();
+            plain.push(c);
+            i += c.len_utf8();
+        }
+    }
+    plain
+}
+
+/// Attributes set by SGR sequences, which are tracked separately.
+const BOLD: usize = 0;
+const DIM: usize = 1;
+const ITALIC: usize = 2;
+const UNDERLINE: usize = 3;
+const BLINK: usize = 4;
+const INVERSE: usize = 5;
+const HIDDEN: usize = 6;
+const STRIKETHROUGH: usize = 7;
+const OVERLINE: usize = 8;
+const FOREGROUND: usize = 9;
+const BACKGROUND: usize = 10;
+const UNDERLINE_COLOR: usize = 11;
+const NUM_ATTRIBUTES: usize = 12;
+
+/// The SGR attributes in effect, each with the parameters that set it.
+///
+/// Parameters setting any other attribute are not tracked.
+#[derive(Default)]
+struct Style([Option<String>; NUM_ATTRIBUTES]);
+
+impl Style {
+    /// Updates the attributes with the parameters of an SGR sequence.
+    fn apply(&mut self, params: &str) {
+        let mut params = params.split(';');
+        while let Some(param) = params.next() {
+            let mut value = String::from(param);
+            let code = match param.split(':').next().unwrap() {
+                "" => 0,
+                code => match code.parse::<u8>() {
+                    Ok(code) => code,
+                    Err(_) => continue,
+                },
+            };
+            let (attribute, is_set) = match code {
+                0 => {
+                    *self = Self::default();
+                    continue;
+                }
+                1 => (BOLD, true),
+                2 => (DIM, true),
+                3 => (ITALIC, true),
+                // Underline styles are given as a sub-parameter, where 0 is none
+                4 => (UNDERLINE, param != "4:0"),
+                5 | 6 => (BLINK, true),
+                7 => (INVERSE, true),
+                8 => (HIDDEN, true),
+                9 => (STRIKETHROUGH, true),
+                21 => (UNDERLINE, true),
+                22 => {
+                    self.0[DIM] = None;
+                    (BOLD, false)
+                }
+                23 => (ITALIC, false),
+                24 => (UNDERLINE, false),
+                25 => (BLINK, false),
+                27 => (INVERSE, false),
+                28 => (HIDDEN, false),
+                29 => (STRIKETHROUGH, false),
+                30..=37 | 90..=97 => (FOREGROUND, true),
+                39 => (FOREGROUND, false),
+                40..=47 | 100..=107 => (BACKGROUND, true),
+                49 => (BACKGROUND, false),
+                53 => (OVERLINE, true),
+                55 => (OVERLINE, false),
+                38 | 48 | 58 => {
+                    // Extended colors without sub-parameters span the following parameters
+                    if !param.contains(':') {
+                        let len = match params.next() {
+                            Some("5") => 1,
+                            Some("2") => 3,
+                            _ => continue,
+                        };
+                        value.push_str(if len == 1 { ";5" } else { ";2" });
+                        for param in params.by_ref().take(len) {
+                            value.push(';');
+                            value.push_str(param);
+                        }
+                    }
+                    let attribute = match code {
+                        38 => FOREGROUND,
+                        48 => BACKGROUND,
+                        _ => UNDERLINE_COLOR,
+                    };
+                    (attribute, true)
+                }
+                59 => (UNDERLINE_COLOR, false),
+                _ => continue,
+            };
+            self.0[attribute] = if is_set { Some(value) } else { None };
+        }
+    }
+
+    /// Returns whether any attribute is set.
+    fn is_active(&self) -> bool {
+        self.0.iter().any(Option::is_some)
+    }
+
+    /// Appends a single SGR sequence setting all attributes in effect to `out`.
+    fn write_to(&self, out: &mut String) {
+        if !self.is_active() {
+            return;
+        }
+        out.push_str("\x1B[");
+        for (i, value) in self.0.iter().flatten().enumerate() {
+            if i > 0 {
+                out.push(';');
+            }
+            out.push_str(value);
+        }
+        out.push('m');
+    }
+
+    /// Copies the escape sequences in the string to `out`, while keeping track of the style.
+    fn copy_escapes(&mut self, s: &str, out: &mut String, copy_text: bool) {
+        let mut i = 0;
+        while i < s.len() {
+            if let Some(len) = escape_len(&s[i..]) {
+                let seq = &s[i..i + len];
+                if let Some(params) = seq.strip_prefix("\x1B[").and_then(|x| x.strip_suffix('m')) {
+                    self.apply(params);
+                }
+                out.push_str(seq);
+                i += len;
+            } else {
+                let c = s[i..].chars().next().unwrap();
+                if copy_text {
+                    out.push(c);
+                }
+                i += c.len_utf8();
+            }
+        }
+    }
+}
+
+/// Wraps the string containing escape sequences greedily into lines of at most `max_columns`
+/// terminal columns.
+///
+/// Escape sequences act as zero width glue: They never introduce break opportunities and are
+/// kept with the text following them. Lines are returned without trailing whitespace and line
+/// terminators. Styles set by SGR sequences are reset at the end of every line they are active
+/// on, and reopened at the start of the next one, so that they do not bleed into margins.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::ansi::wrap_ansi;
+/// let lines = wrap_ansi("\x1B[31mred text\x1B[0m here", 4);
+/// assert_eq!(lines, ["\x1B[31mred\x1B[0m", "\x1B[31mtext\x1B[0m", "here"]);
+/// ```
+pub fn wrap_ansi(s: &str, max_columns: usize) -> Vec<String> {
+    let mut plain = String::with_capacity(s.len());
+    // Index in the original string after each byte of the plain text
+    let mut ends = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        if let Some(len) = escape_len(&s[i..]) {
+            i += len;
+        } else {
+            let c = s[i..].chars().next().unwrap();
+            plain.push(c);
+            ends.extend(i + 1..=i + c.len_utf8());
+            i += c.len_utf8();
+        }
+    }
+    // Maps a plain text index to the original one, placing escape sequences after it
+    let original_index = |i: usize| if i == 0 { 0 } else { ends[i - 1] };
+
+    let mut style = Style::default();
+    wrap_greedy(&plain, max_columns, column_width)
+        .map(|(range, _)| {
+            let start = original_index(range.start);
+            let end = if range.end == plain.len() {
+                s.len()
+            } else {
+                original_index(range.end)
+            };
+            let visible_len = trim_trailing_whitespace(&plain[range.clone()]).len();
+            let visible_end = if visible_len == 0 {
+                start
+            } else {
+                original_index(range.start + visible_len)
+            };
+
+            let mut line = String::new();
+            style.write_to(&mut line);
+            style.copy_escapes(&s[start..visible_end], &mut line, true);
+            style.copy_escapes(&s[visible_end..end], &mut line, false);
+            if style.is_active() {
+                line.push_str("\x1B[0m");
+            }
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_are_glue() {
+        // Does not break within "a\x1B[1mb", nor measure the sequence
+        let lines = wrap_ansi("x a\x1B[1mb\x1B[22m c", 2);
+        assert_eq!(lines, ["x", "a\x1B[1mb\x1B[22m", "c"]);
+    }
+
+    #[test]
+    fn reopens_active_attributes() {
+        let lines = wrap_ansi("\x1B[1;38;5;196ma \x1B[3mb \x1B[22;39mc \x1B[0md", 1);
+        assert_eq!(
+            lines,
+            [
+                "\x1B[1;38;5;196ma\x1B[0m",
+                "\x1B[1;38;5;196m\x1B[3mb\x1B[0m",
+                "\x1B[1;3;38;5;196m\x1B[22;39mc\x1B[0m",
+                "\x1B[3m\x1B[0md",
+            ]
+        );
+        let lines = wrap_ansi("\x1B[4:3;38:2::0:0:255ma \x1B[4:0mb", 1);
+        assert_eq!(
+            lines,
+            [
+                "\x1B[4:3;38:2::0:0:255ma\x1B[0m",
+                "\x1B[4:3;38:2::0:0:255m\x1B[4:0mb\x1B[0m",
+            ]
+        );
+    }
+
+    #[test]
+    fn osc_hyperlink() {
+        let s = "\x1B]8;;https://example.com\x1B\\link\x1B]8;;\x07 text";
+        assert_eq!(strip_escapes(s), "link text");
+        assert_eq!(wrap_ansi(s, 4).len(), 2);
+    }
+}
@@ -0,0 +1,330 @@
+//! Hyphenation using TeX-style patterns as described by [Liang][Liang83].
+//!
+//! Patterns are not bundled with this crate, and have to be supplied by the caller, e.g. from the
+//! `hyph-*.pat.txt` files of the [hyph-utf8] project.
+//!
+//! [Liang83]: https://tug.org/docs/liang/
+//! [hyph-utf8]: https://ctan.org/pkg/hyph-utf8
+
+use crate::{break_property, linebreaks, BreakClass, BreakOpportunity, Merge};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::{cmp::max, fmt, str};
+
+/// An error which can be returned when parsing hyphenation patterns.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PatternsError {
+    /// The input is not valid UTF-8.
+    InvalidUtf8,
+    /// A pattern or exception on the given 1-based line is malformed.
+    InvalidPattern(usize),
+}
+
+impl fmt::Display for PatternsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternsError::InvalidUtf8 => f.write_str("hyphenation patterns are not valid UTF-8"),
+            PatternsError::InvalidPattern(line) => {
+                write!(f, "invalid hyphenation pattern on line {}", line)
+            }
+        }
+    }
+}
+
+/// A set of Liang hyphenation patterns together with hyphenation exceptions.
+#[derive(Clone, Debug)]
+pub struct Patterns {
+    /// Map from pattern letters, with `.` marking word boundaries, to inter-letter priorities.
+    patterns: BTreeMap<String, Vec<u8>>,
+    /// Map from exception words to the char indices of their hyphens.
+    exceptions: BTreeMap<String, Vec<usize>>,
+    /// Length in chars of the longest pattern.
+    max_len: usize,
+    left_min: usize,
+    right_min: usize,
+}
+
+impl Patterns {
+    /// Parses patterns in the TeX format.
+    ///
+    /// Accepts either bare whitespace-separated patterns, as in `hyph-*.pat.txt` files, or the
+    /// `\patterns{...}` and `\hyphenation{...}` commands of `hyph-*.tex` files. Comments start
+    /// with `%`. The minimum number of letters before and after a hyphen default to 2 and 3
+    /// respectively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use unicode_linebreak::hyphenation::Patterns;
+    /// let patterns = Patterns::parse(br"\patterns{ a1n } \hyphenation{ ta-ble }").unwrap();
+    /// assert!(patterns.hyphenate("bananas").eq([2, 4]));
+    /// assert!(patterns.hyphenate("table").eq([2]));
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Self, PatternsError> {
+        let s = str::from_utf8(bytes).map_err(|_| PatternsError::InvalidUtf8)?;
+        let mut result = Patterns {
+            patterns: BTreeMap::new(),
+            exceptions: BTreeMap::new(),
+            max_len: 0,
+            left_min: 2,
+            right_min: 3,
+        };
+
+        let mut in_exceptions = false;
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.split('%').next().unwrap_or_default();
+            for token in line.split_whitespace() {
+                let mut token = token;
+                if let Some(rest) = token.strip_prefix(r"\patterns{") {
+                    in_exceptions = false;
+                    token = rest;
+                } else if let Some(rest) = token.strip_prefix(r"\hyphenation{") {
+                    in_exceptions = true;
+                    token = rest;
+                }
+                let closes_group = if let Some(rest) = token.strip_suffix('}') {
+                    token = rest;
+                    true
+                } else {
+                    false
+                };
+
+                let ok = token.is_empty()
+                    || if in_exceptions {
+                        result.add_exception(token)
+                    } else {
+                        result.add_pattern(token)
+                    };
+                if !ok {
+                    return Err(PatternsError::InvalidPattern(line_idx + 1));
+                }
+                // Bare patterns may follow the end of the group
+                if closes_group {
+                    in_exceptions = false;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Sets the minimum number of letters before and after a hyphenation point.
+    pub fn set_hyphen_mins(&mut self, left_min: usize, right_min: usize) {
+        self.left_min = max(left_min, 1);
+        self.right_min = max(right_min, 1);
+    }
+
+    fn add_pattern(&mut self, token: &str) -> bool {
+        let mut letters = String::new();
+        let mut priorities = vec![0];
+        for c in token.chars() {
+            if let Some(d) = c.to_digit(10) {
+                *priorities.last_mut().unwrap() = d as u8;
+            } else if c.is_alphabetic() || c == '.' || c == '\'' {
+                letters.extend(c.to_lowercase());
+                priorities.push(0);
+            } else {
+                return false;
+            }
+        }
+        if letters.is_empty() {
+            return false;
+        }
+        self.max_len = max(self.max_len, priorities.len() - 1);
+        self.patterns.insert(letters, priorities);
+        true
+    }
+
+    fn add_exception(&mut self, token: &str) -> bool {
+        let mut word = String::new();
+        let mut hyphens = Vec::new();
+        let mut len = 0;
+        for c in token.chars() {
+            if c == '-' {
+                hyphens.push(len);
+            } else {
+                word.extend(c.to_lowercase());
+                len += 1;
+            }
+        }
+        if word.is_empty() {
+            return false;
+        }
+        self.exceptions.insert(word, hyphens);
+        true
+    }
+
+    /// Returns the byte indices of the hyphenation points in the specified word.
+    pub fn hyphenate<'a>(&self, word: &'a str) -> impl Iterator<Item = usize> + 'a {
+        let chars: Vec<char> = word.chars().map(to_lower).collect();
+        let n = chars.len();
+
+        let mut is_hyphen = vec![false; n];
+        if n >= self.left_min + self.right_min {
+            let lowered: String = chars.iter().collect();
+            if let Some(hyphens) = self.exceptions.get(&lowered) {
+                for &k in hyphens.iter().filter(|&&k| k < n) {
+                    is_hyphen[k] = true;
+                }
+            } else {
+                let padded: Vec<char> = ['.'].iter().chain(&chars).chain(&['.']).copied().collect();
+                let mut priorities = vec![0; padded.len() + 1];
+                let mut key = String::new();
+                for i in 0..padded.len() {
+                    key.clear();
+                    for &c in padded[i..].iter().take(self.max_len) {
+                        key.push(c);
+                        if let Some(values) = self.patterns.get(&key) {
+                            for (p, &v) in priorities[i..].iter_mut().zip(values) {
+                                *p = max(*p, v);
+                            }
+                        }
+                    }
+                }
+                // Priority between padded[k] and padded[k + 1] is the one before word char k
+                for (k, x) in is_hyphen.iter_mut().enumerate() {
+                    *x = priorities[k + 1] % 2 == 1;
+                }
+            }
+            is_hyphen[..self.left_min].fill(false);
+            is_hyphen[n + 1 - self.right_min..].fill(false);
+        }
+
+        word.char_indices()
+            .zip(is_hyphen)
+            .filter_map(|((i, _), is_hyphen)| if is_hyphen { Some(i) } else { None })
+    }
+}
+
+fn to_lower(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Returns whether the character may be part of a hyphenatable word.
+fn is_word_char(c: char) -> bool {
+    matches!(
+        break_property(c as u32),
+        BreakClass::Alphabetic | BreakClass::HebrewLetter
+    ) && c.is_alphabetic()
+}
+
+/// Break opportunity type, as returned by [`linebreaks_hyphenated`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HyphenatedBreak {
+    /// A break opportunity found by [`linebreaks`].
+    Break(BreakOpportunity),
+    /// A line is allowed to end at this spot if a hyphen is inserted.
+    Hyphen,
+}
+
+impl crate::wrap::Break for HyphenatedBreak {
+    fn is_mandatory(self) -> bool {
+        self == HyphenatedBreak::Break(BreakOpportunity::Mandatory)
+    }
+}
+
+/// Returns an iterator over line break opportunities in the specified string, including
+/// hyphenation points.
+///
+/// Behaves as [`linebreaks`], but additionally yields [`HyphenatedBreak::Hyphen`] breaks inside
+/// runs of alphabetic AL and HL characters, as determined by the given patterns.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::hyphenation::{linebreaks_hyphenated, HyphenatedBreak::*, Patterns};
+/// use unicode_linebreak::BreakOpportunity::{Allowed, Mandatory};
+/// let patterns = Patterns::parse(b"a1n").unwrap();
+/// assert!(linebreaks_hyphenated("a bananas", &patterns).eq([
+///     (2, Break(Allowed)),
+///     (4, Hyphen),
+///     (6, Hyphen),
+///     (9, Break(Mandatory)),
+/// ]));
+/// ```
+pub fn linebreaks_hyphenated<'a>(
+    s: &'a str,
+    patterns: &'a Patterns,
+) -> impl Iterator<Item = (usize, HyphenatedBreak)> + 'a {
+    let mut rest = s;
+    let words = core::iter::from_fn(move || {
+        let start = rest.find(is_word_char)?;
+        let len = rest[start..]
+            .find(|c| !is_word_char(c))
+            .unwrap_or(rest.len() - start);
+        let offset = s.len() - rest.len() + start;
+        let word = &rest[start..][..len];
+        rest = &rest[start + len..];
+        Some(patterns.hyphenate(word).map(move |i| offset + i))
+    });
+    let hyphens = words.flatten().map(|i| (i, HyphenatedBreak::Hyphen));
+
+    Merge {
+        a: linebreaks(s)
+            .map(|(i, opportunity)| (i, HyphenatedBreak::Break(opportunity)))
+            .peekable(),
+        b: hyphens.peekable(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hyphen_mins() {
+        let mut patterns = Patterns::parse(b"1a").unwrap();
+        assert!(patterns.hyphenate("aaaaaa").eq([2, 3]));
+        patterns.set_hyphen_mins(1, 1);
+        assert!(patterns.hyphenate("aaaaaa").eq(1..6));
+    }
+
+    #[test]
+    fn optimal_fit_hyphen_penalty() {
+        use crate::wrap::wrap_optimal_fit_with_breaks;
+        use BreakOpportunity::{Allowed, Mandatory};
+        use HyphenatedBreak::{Break, Hyphen};
+
+        let patterns = Patterns::parse(b"a1n").unwrap();
+        let text = "a bananas";
+        let wrap = |max_width, cost| {
+            let breaks = linebreaks_hyphenated(text, &patterns);
+            let penalty = |_, x| if x == Hyphen { cost } else { 0.0 };
+            wrap_optimal_fit_with_breaks(text, breaks, max_width, str::len, penalty)
+        };
+        assert_eq!(wrap(7, 0.0), [(0..6, Hyphen), (6..9, Break(Mandatory))]);
+        assert_eq!(
+            wrap(7, 1.0),
+            [(0..2, Break(Allowed)), (2..9, Break(Mandatory))]
+        );
+        // Hyphenating is still preferred over overflowing
+        assert_eq!(wrap(5, 1e6), [(0..4, Hyphen), (4..9, Break(Mandatory))]);
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert_eq!(
+            Patterns::parse(b"% comment\na1b\nb-c").unwrap_err(),
+            PatternsError::InvalidPattern(3)
+        );
+        // Not an exception after the end of the group
+        assert_eq!(
+            Patterns::parse(br"\hyphenation{ ta-ble } a-n").unwrap_err(),
+            PatternsError::InvalidPattern(1)
+        );
+    }
+
+    #[test]
+    fn patterns_after_exceptions() {
+        for s in [
+            &br"\hyphenation{ ta-ble } a1n"[..],
+            b"\\hyphenation{ta-ble}\na1n",
+        ] {
+            let patterns = Patterns::parse(s).unwrap();
+            assert!(patterns.hyphenate("bananas").eq([2, 4]));
+            assert!(patterns.hyphenate("table").eq([2]));
+        }
+    }
+}
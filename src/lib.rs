@@ -21,7 +21,22 @@
 #![no_std]
 #![deny(missing_docs, missing_debug_implementations)]
 
-use core::iter::once;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::{
+    iter::{from_fn, once, Peekable},
+    str::Chars,
+};
+
+#[cfg(feature = "alloc")]
+pub mod ansi;
+#[cfg(feature = "hyphenation")]
+pub mod hyphenation;
+pub mod runs;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+pub mod wrap;
 
 /// The [Unicode version](https://www.unicode.org/versions/) conformed to.
 pub const UNICODE_VERSION: (u8, u8, u8) = (15, 0, 0);
@@ -29,37 +44,178 @@ pub const UNICODE_VERSION: (u8, u8, u8) = (15, 0, 0);
 include!("shared.rs");
 include!("tables.rs");
 
-/// Returns the line break property of the specified code point.
-///
-/// # Examples
-///
-/// ```
-/// use unicode_linebreak::{BreakClass, break_property};
-/// assert_eq!(break_property(0x2CF3), BreakClass::Alphabetic);
-/// ```
+/// Looks up the value of the code point in a trie generated by `gen-tables`.
 #[inline(always)]
-pub fn break_property(codepoint: u32) -> BreakClass {
+fn trie_lookup<T: Copy>(
+    index: &[u16],
+    data: &[T],
+    high_start: u32,
+    default: T,
+    codepoint: u32,
+) -> T {
     const BMP_INDEX_LENGTH: u32 = BMP_LIMIT >> BMP_SHIFT;
     const OMITTED_BMP_INDEX_1_LENGTH: u32 = BMP_LIMIT >> SHIFT_1;
 
     let data_pos = if codepoint < BMP_LIMIT {
         let i = codepoint >> BMP_SHIFT;
-        BREAK_PROP_TRIE_INDEX[i as usize] + (codepoint & (BMP_DATA_BLOCK_LENGTH - 1)) as u16
-    } else if codepoint < BREAK_PROP_TRIE_HIGH_START {
+        index[i as usize] + (codepoint & (BMP_DATA_BLOCK_LENGTH - 1)) as u16
+    } else if codepoint < high_start {
         let i1 = codepoint >> SHIFT_1;
-        let i2 = BREAK_PROP_TRIE_INDEX
-            [(i1 + BMP_INDEX_LENGTH - OMITTED_BMP_INDEX_1_LENGTH) as usize]
+        let i2 = index[(i1 + BMP_INDEX_LENGTH - OMITTED_BMP_INDEX_1_LENGTH) as usize]
             + ((codepoint >> SHIFT_2) & (INDEX_2_BLOCK_LENGTH - 1)) as u16;
-        let i3_block = BREAK_PROP_TRIE_INDEX[i2 as usize];
+        let i3_block = index[i2 as usize];
         let i3_pos = ((codepoint >> SHIFT_3) & (INDEX_3_BLOCK_LENGTH - 1)) as u16;
 
         debug_assert!(i3_block & 0x8000 == 0, "18-bit indices are unexpected");
-        let data_block = BREAK_PROP_TRIE_INDEX[(i3_block + i3_pos) as usize];
+        let data_block = index[(i3_block + i3_pos) as usize];
         data_block + (codepoint & (SMALL_DATA_BLOCK_LENGTH - 1)) as u16
     } else {
-        return XX;
+        return default;
     };
-    BREAK_PROP_TRIE_DATA[data_pos as usize]
+    data[data_pos as usize]
+}
+
+/// Returns the line break property of the specified code point.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{BreakClass, break_property};
+/// assert_eq!(break_property(0x2CF3), BreakClass::Alphabetic);
+/// ```
+#[inline(always)]
+pub fn break_property(codepoint: u32) -> BreakClass {
+    trie_lookup(
+        &BREAK_PROP_TRIE_INDEX,
+        &BREAK_PROP_TRIE_DATA,
+        BREAK_PROP_TRIE_HIGH_START,
+        XX,
+        codepoint,
+    )
+}
+
+/// Returns an iterator over the line break properties of the characters in the string.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{break_properties, BreakClass::*};
+/// assert!(break_properties("a 1").eq([Alphabetic, Space, Numeric]));
+/// ```
+pub fn break_properties(s: &str) -> impl Iterator<Item = BreakClass> + Clone + '_ {
+    let mut i = 0;
+    from_fn(move || {
+        if i < s.len() {
+            let (cls, len) = class_at(s, i);
+            i += len;
+            Some(cls)
+        } else {
+            None
+        }
+    })
+}
+
+/// Writes the line break properties of the characters in the string to the buffer, returning the
+/// number of characters.
+///
+/// Gives the same classes as [`break_properties`], without requiring an allocation to collect
+/// them. A buffer at least as long as the string in bytes always has room for every character.
+///
+/// With the `simd` feature, on x86-64 CPUs supporting SSSE3, runs of ASCII characters are
+/// classified 16 at a time.
+///
+/// # Panics
+///
+/// Panics if the buffer is shorter than the number of characters.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{break_properties_into, BreakClass::*};
+/// let s = "a\u{3000}1";
+/// let mut out = [Unknown; 5];
+/// let n = break_properties_into(s, &mut out);
+/// assert_eq!(out[..n], [Alphabetic, After, Numeric]);
+/// ```
+pub fn break_properties_into(s: &str, out: &mut [BreakClass]) -> usize {
+    let mut n = 0;
+    let mut chars = s.chars();
+    #[allow(clippy::while_let_on_iterator)] // Reassigned with the `simd` feature
+    while let Some(c) = chars.next() {
+        *out.get_mut(n).expect("buffer too short") = break_property(c as u32);
+        n += 1;
+        // Only ASCII characters are likely to be followed by more of them
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if c.is_ascii() {
+            let rest = chars.as_str();
+            let len = simd::classify_ascii(rest.as_bytes(), &mut out[n..]);
+            chars = rest[len..].chars();
+            n += len;
+        }
+    }
+    n
+}
+
+/// Returns the East Asian Width property of the specified code point.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{east_asian_width, EastAsianWidth};
+/// assert_eq!(east_asian_width('a' as u32), EastAsianWidth::Narrow);
+/// assert_eq!(east_asian_width('中' as u32), EastAsianWidth::Wide);
+/// ```
+#[inline(always)]
+pub fn east_asian_width(codepoint: u32) -> EastAsianWidth {
+    trie_lookup(
+        &EAST_ASIAN_WIDTH_TRIE_INDEX,
+        &EAST_ASIAN_WIDTH_TRIE_DATA,
+        EAST_ASIAN_WIDTH_TRIE_HIGH_START,
+        EastAsianWidth::Neutral,
+        codepoint,
+    )
+}
+
+/// Returns the Grapheme_Cluster_Break property of the specified code point.
+#[inline(always)]
+pub(crate) fn grapheme_cluster_break(codepoint: u32) -> GraphemeClusterBreak {
+    trie_lookup(
+        &GRAPHEME_CLUSTER_BREAK_TRIE_INDEX,
+        &GRAPHEME_CLUSTER_BREAK_TRIE_DATA,
+        GRAPHEME_CLUSTER_BREAK_TRIE_HIGH_START,
+        GraphemeClusterBreak::Other,
+        codepoint,
+    )
+}
+
+/// Returns the number of columns the string occupies when displayed on a terminal.
+///
+/// Characters with an East Asian Width of [`Wide`](EastAsianWidth::Wide) or
+/// [`Fullwidth`](EastAsianWidth::Fullwidth) take up two columns, while control characters and
+/// characters extending the preceding one into a grapheme cluster, such as combining marks, emoji
+/// modifiers and Hangul medial and final jamo, take up none. All others, including those of
+/// [`Ambiguous`](EastAsianWidth::Ambiguous) width, take up one column.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::column_width;
+/// assert_eq!(column_width("abc"), 3);
+/// assert_eq!(column_width("日本語"), 6);
+/// assert_eq!(column_width("e\u{301}"), 1);
+/// ```
+pub fn column_width(s: &str) -> usize {
+    use GraphemeClusterBreak::*;
+    s.chars()
+        .map(|c| match grapheme_cluster_break(c as u32) {
+            CarriageReturn | LineFeed | Control | Extend | ZeroWidthJoiner => 0,
+            HangulVJamo | HangulTJamo => 0,
+            _ => match east_asian_width(c as u32) {
+                EastAsianWidth::Wide | EastAsianWidth::Fullwidth => 2,
+                _ => 1,
+            },
+        })
+        .sum()
 }
 
 /// Break opportunity type.
@@ -71,6 +227,100 @@ pub enum BreakOpportunity {
     Allowed,
 }
 
+/// State of the line breaking algorithm, for finding break opportunities one character at a time.
+///
+/// This allows feeding line breaking classes obtained elsewhere, e.g. per cluster from a text
+/// shaper, without looking up the characters again.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{LineBreakState, BreakClass::{Alphabetic, Space}};
+/// use unicode_linebreak::BreakOpportunity::{Mandatory, Allowed};
+/// let mut state = LineBreakState::new();
+/// assert_eq!(state.step(Alphabetic), None);
+/// assert_eq!(state.step(Space), None);
+/// assert_eq!(state.step(Alphabetic), Some(Allowed));
+/// assert_eq!(state.finish(), Some(Mandatory));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct LineBreakState {
+    state: u8,
+    /// Whether the previous character was a ZWJ, handled outside the table to reduce its size.
+    is_zwj: bool,
+}
+
+impl Default for LineBreakState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineBreakState {
+    /// Creates the state at the start of text (`sot`).
+    pub const fn new() -> Self {
+        Self {
+            state: sot,
+            is_zwj: false,
+        }
+    }
+
+    #[inline]
+    fn transition(&mut self, cls: u8) -> Option<BreakOpportunity> {
+        let val = PAIR_TABLE[self.state as usize][cls as usize];
+        let is_mandatory = val & MANDATORY_BREAK_BIT != 0;
+        let is_break = val & ALLOWED_BREAK_BIT != 0 && (!self.is_zwj || is_mandatory);
+        self.state = val & !(ALLOWED_BREAK_BIT | MANDATORY_BREAK_BIT);
+        self.is_zwj = cls == ZWJ as u8;
+
+        if is_break {
+            Some(if is_mandatory {
+                BreakOpportunity::Mandatory
+            } else {
+                BreakOpportunity::Allowed
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Advances past the ASCII characters starting at the byte index that leave the state as is
+    /// without a break before them, e.g. the letters of a word, returning the index after them.
+    ///
+    /// As the state stays the same, the lookups in the table do not depend on each other.
+    #[inline(always)]
+    fn skip_ascii(&mut self, bytes: &[u8], mut i: usize) -> usize {
+        let state = self.state;
+        let row = &PAIR_TABLE[state as usize];
+        let start = i;
+        while let Some(&b) = bytes.get(i) {
+            if !b.is_ascii() || row[ASCII_BREAK_PROP[b as usize] as usize] != state {
+                break;
+            }
+            i += 1;
+        }
+        if i > start {
+            self.is_zwj = false;
+        }
+        i
+    }
+
+    /// Advances past a character of the specified class, returning the break opportunity before
+    /// it, if any.
+    #[inline]
+    pub fn step(&mut self, cls: BreakClass) -> Option<BreakOpportunity> {
+        self.transition(cls as u8)
+    }
+
+    /// Ends the text (`eot`), returning the break opportunity at its end, if any.
+    ///
+    /// There is always one unless the text is empty.
+    #[inline]
+    pub fn finish(mut self) -> Option<BreakOpportunity> {
+        self.transition(eot)
+    }
+}
+
 /// Returns an iterator over line break opportunities in the specified string.
 ///
 /// Break opportunities are given as tuples of the byte index of the character succeeding the break
@@ -87,41 +337,488 @@ pub enum BreakOpportunity {
 /// assert!(linebreaks("Hello world!").eq(vec![(6, Allowed), (12, Mandatory)]));
 /// ```
 pub fn linebreaks(s: &str) -> impl Iterator<Item = (usize, BreakOpportunity)> + Clone + '_ {
-    use BreakOpportunity::{Allowed, Mandatory};
-
-    s.char_indices()
-        .map(|(i, c)| (i, break_property(c as u32) as u8))
-        .chain(once((s.len(), eot)))
-        .scan((sot, false), |state, (i, cls)| {
-            // ZWJ is handled outside the table to reduce its size
-            let val = PAIR_TABLE[state.0 as usize][cls as usize];
-            let is_mandatory = val & MANDATORY_BREAK_BIT != 0;
-            let is_break = val & ALLOWED_BREAK_BIT != 0 && (!state.1 || is_mandatory);
-            *state = (
-                val & !(ALLOWED_BREAK_BIT | MANDATORY_BREAK_BIT),
-                cls == BreakClass::ZeroWidthJoiner as u8,
-            );
+    Linebreaks::new(s)
+}
 
-            Some((i, is_break, is_mandatory))
-        })
-        .filter_map(|(i, is_break, is_mandatory)| {
-            if is_break {
-                Some((i, if is_mandatory { Mandatory } else { Allowed }))
+/// Returns the line breaking class and length of the character at the byte index.
+///
+/// ASCII characters, which make up most text, are looked up in a flat table instead of the trie.
+#[inline(always)]
+fn class_at(s: &str, i: usize) -> (BreakClass, usize) {
+    let bytes = s.as_bytes();
+    let b = bytes[i];
+    if b.is_ascii() {
+        return (ASCII_BREAK_PROP[b as usize], 1);
+    }
+    // Decode the UTF-8 sequence, which is known to be valid
+    let cont = |j: usize| (bytes[i + j] & 0x3F) as u32;
+    let (codepoint, len) = match b {
+        0xC0..=0xDF => ((b as u32 & 0x1F) << 6 | cont(1), 2),
+        0xE0..=0xEF => ((b as u32 & 0x0F) << 12 | cont(1) << 6 | cont(2), 3),
+        _ => (
+            (b as u32 & 0x07) << 18 | cont(1) << 12 | cont(2) << 6 | cont(3),
+            4,
+        ),
+    };
+    (break_property(codepoint), len)
+}
+
+/// Iterator returned by [`linebreaks`].
+#[derive(Clone, Debug)]
+struct Linebreaks<'a> {
+    s: &'a str,
+    /// The characters after the last one stepped past.
+    chars: Chars<'a>,
+    /// The state, or `None` once `eot` has been reached.
+    state: Option<LineBreakState>,
+}
+
+impl<'a> Linebreaks<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s,
+            chars: s.chars(),
+            state: Some(LineBreakState::new()),
+        }
+    }
+
+    /// Returns the byte index of the next character.
+    fn index(&self) -> usize {
+        self.s.len() - self.chars.as_str().len()
+    }
+}
+
+impl Iterator for Linebreaks<'_> {
+    type Item = (usize, BreakOpportunity);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state.as_mut()?;
+        while let Some(c) = self.chars.next() {
+            let cls = if c.is_ascii() {
+                ASCII_BREAK_PROP[c as usize]
             } else {
-                None
+                break_property(c as u32)
+            };
+            if let Some(opportunity) = state.step(cls) {
+                return Some((self.index() - c.len_utf8(), opportunity));
+            }
+            // Only ASCII characters are likely to be followed by more of them
+            if c.is_ascii() {
+                let rest = self.chars.as_str();
+                if rest.as_bytes().first().map_or(false, u8::is_ascii) {
+                    let n = state.skip_ascii(rest.as_bytes(), 0);
+                    self.chars = rest[n..].chars();
+                }
+            }
+        }
+        self.state.take()?.finish().map(|x| (self.s.len(), x))
+    }
+}
+
+/// Returns an iterator over line break opportunities in the specified string, as [`linebreaks`]
+/// does, with each Contingent (CB) character resolved to another class by the callback.
+///
+/// The callback is given the byte index of each CB character, such as the object replacement
+/// character U+FFFC used for embedded objects, and returns the class to treat it as, per LB1.
+/// Returning [`BreakClass::Contingent`] keeps the default of breaking before and after it.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{linebreaks_with_contingent, BreakClass, BreakOpportunity::*};
+/// // An inline image that glues to its neighbors and one that stands alone
+/// let s = "a\u{FFFC}b\u{FFFC}c";
+/// let breaks = linebreaks_with_contingent(s, |i| {
+///     if i == 1 { BreakClass::Alphabetic } else { BreakClass::Contingent }
+/// });
+/// assert!(breaks.eq([(5, Allowed), (8, Allowed), (9, Mandatory)]));
+/// ```
+pub fn linebreaks_with_contingent<'a>(
+    s: &'a str,
+    resolve: impl Fn(usize) -> BreakClass + Clone + 'a,
+) -> impl Iterator<Item = (usize, BreakOpportunity)> + Clone + 'a {
+    let classes = s.char_indices().map(move |(i, c)| {
+        let cls = break_property(c as u32);
+        (i, if cls == CB { resolve(i) } else { cls })
+    });
+    break_opportunities(classes, s.len())
+}
+
+/// Length in bytes below which [`par_linebreaks`] does not split text further.
+#[cfg(feature = "rayon")]
+const PAR_MIN_CHUNK_LEN: usize = 16 * 1024;
+
+/// Returns a parallel iterator over line break opportunities in the specified string.
+///
+/// The string is split into chunks at safe pairs, as found by [`split_at_safe`], which are then
+/// processed in parallel. The break opportunities are exactly the same as those returned by
+/// [`linebreaks`], and are kept in order when collected.
+///
+/// Requires the `rayon` feature.
+///
+/// # Examples
+///
+/// ```
+/// use rayon::prelude::*;
+/// use unicode_linebreak::{linebreaks, par_linebreaks};
+/// let s = "The quick brown fox jumps over the lazy dog. ".repeat(1000);
+/// let breaks: Vec<_> = par_linebreaks(&s).collect();
+/// assert!(breaks.into_iter().eq(linebreaks(&s)));
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_linebreaks(
+    s: &str,
+) -> impl rayon::iter::ParallelIterator<Item = (usize, BreakOpportunity)> + '_ {
+    par_linebreaks_chunked(s, PAR_MIN_CHUNK_LEN)
+}
+
+#[cfg(feature = "rayon")]
+fn par_linebreaks_chunked(
+    s: &str,
+    min_chunk_len: usize,
+) -> impl rayon::iter::ParallelIterator<Item = (usize, BreakOpportunity)> + '_ {
+    use rayon::iter::ParallelIterator;
+
+    let chunks = rayon::iter::split(0..s.len(), move |chunk| {
+        if chunk.len() < 2 * min_chunk_len {
+            return (chunk, None);
+        }
+        let mut mid = chunk.start + chunk.len() / 2;
+        while !s.is_char_boundary(mid) {
+            mid += 1;
+        }
+        let (prev, _) = split_at_safe(&s[chunk.start..mid]);
+        if prev.is_empty() {
+            return (chunk, None);
+        }
+        let mid = chunk.start + prev.len();
+        (chunk.start..mid, Some(mid..chunk.end))
+    });
+    chunks.flat_map_iter(move |chunk| {
+        // Breaks after the first character of a chunk do not depend on prior context, whereas
+        // the one before it is found together with the previous chunk
+        let end = chunk.end + s[chunk.end..].chars().next().map_or(0, char::len_utf8);
+        linebreaks(&s[chunk.start..end])
+            .map(move |(i, opportunity)| (chunk.start + i, opportunity))
+            .filter(move |&(i, _)| i <= chunk.end)
+    })
+}
+
+/// Status of a position in a string, as written by [`linebreaks_into`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
+pub enum BreakStatus {
+    /// A line must not break at this spot, or it is not a character boundary.
+    NoBreak = 0,
+    /// A line is allowed to end at this spot.
+    Allowed = 1,
+    /// A line must break at this spot.
+    Mandatory = 2,
+}
+
+impl From<Option<BreakOpportunity>> for BreakStatus {
+    fn from(opportunity: Option<BreakOpportunity>) -> Self {
+        match opportunity {
+            None => BreakStatus::NoBreak,
+            Some(BreakOpportunity::Mandatory) => BreakStatus::Mandatory,
+            Some(_) => BreakStatus::Allowed,
+        }
+    }
+}
+
+impl From<BreakStatus> for u8 {
+    fn from(status: BreakStatus) -> Self {
+        status as u8
+    }
+}
+
+/// Writes the break status of every byte index of the specified string, including its end, to
+/// the buffer.
+///
+/// The buffer may hold either [`BreakStatus`] values or their `u8` representations. Unlike
+/// [`linebreaks`], no iterator needs to be pulled, which suits foreign function interfaces and
+/// hot loops.
+///
+/// # Panics
+///
+/// Panics if the length of the buffer is not one more than that of the string.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{linebreaks_into, BreakStatus::*};
+/// let mut out = [NoBreak; 6];
+/// linebreaks_into("a b\nc", &mut out);
+/// assert_eq!(out, [NoBreak, NoBreak, Allowed, NoBreak, Mandatory, Mandatory]);
+///
+/// let mut out = [0u8; 4];
+/// linebreaks_into("é ", &mut out);
+/// assert_eq!(out, [0, 0, 0, 2]);
+/// ```
+pub fn linebreaks_into<T: From<BreakStatus>>(s: &str, out: &mut [T]) {
+    assert_eq!(
+        out.len(),
+        s.len() + 1,
+        "buffer length must be one more than string length"
+    );
+    let bytes = s.as_bytes();
+    let mut state = LineBreakState::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let (cls, len) = class_at(s, i);
+        out[i] = BreakStatus::from(state.step(cls)).into();
+        for x in &mut out[i + 1..i + len] {
+            *x = BreakStatus::NoBreak.into();
+        }
+        i += len;
+        // Only ASCII characters are likely to be followed by more of them
+        if len == 1 && bytes.get(i).map_or(false, u8::is_ascii) {
+            let j = state.skip_ascii(bytes, i);
+            for x in &mut out[i..j] {
+                *x = BreakStatus::NoBreak.into();
             }
+            i = j;
+        }
+    }
+    out[s.len()] = BreakStatus::from(state.finish()).into();
+}
+
+/// Returns break opportunities given the positions and line breaking classes of the characters of
+/// a text, and the position of its end.
+fn break_opportunities<P: Copy>(
+    classes: impl Iterator<Item = (P, BreakClass)> + Clone,
+    end: P,
+) -> impl Iterator<Item = (P, BreakOpportunity)> + Clone {
+    classes
+        .map(|(i, cls)| (i, cls as u8))
+        .chain(once((end, eot)))
+        .scan(LineBreakState::new(), |state, (i, cls)| {
+            Some((i, state.transition(cls)))
         })
+        .filter_map(|(i, opportunity)| opportunity.map(|x| (i, x)))
+}
+
+/// Text between two consecutive break opportunities.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Segment<'a> {
+    /// The text, excluding trailing whitespace.
+    pub text: &'a str,
+    /// The trailing whitespace and line terminators, which are allowed to hang past the end of
+    /// a line.
+    pub trailing_whitespace: &'a str,
+    /// The type of the break opportunity after the segment.
+    pub break_after: BreakOpportunity,
+}
+
+/// Returns an iterator over the segments of the string between consecutive break opportunities.
+///
+/// The segments cover the whole string, and are derived from [`linebreaks`].
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{segments, Segment, BreakOpportunity::{Mandatory, Allowed}};
+/// assert!(segments("Hello  world\n").eq([
+///     Segment { text: "Hello", trailing_whitespace: "  ", break_after: Allowed },
+///     Segment { text: "world", trailing_whitespace: "\n", break_after: Mandatory },
+/// ]));
+/// ```
+pub fn segments(s: &str) -> impl Iterator<Item = Segment<'_>> + Clone {
+    linebreaks(s).scan(0, move |start, (i, break_after)| {
+        let segment = &s[*start..i];
+        *start = i;
+        let text = wrap::trim_trailing_whitespace(segment);
+        Some(Segment {
+            text,
+            trailing_whitespace: &segment[text.len()..],
+            break_after,
+        })
+    })
+}
+
+/// Processing of white space, as controlled by the CSS
+/// [`white-space`](https://www.w3.org/TR/css-text-3/#white-space-property) property.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum WhiteSpace {
+    /// Collapse white space, including newlines, and wrap lines at break opportunities.
+    Normal,
+    /// Preserve white space, and only break lines at newlines.
+    Pre,
+    /// Collapse white space, including newlines, and never wrap lines.
+    Nowrap,
+    /// Preserve white space, and wrap lines at break opportunities.
+    PreWrap,
+    /// Collapse white space except newlines, and wrap lines at break opportunities.
+    PreLine,
+    /// Preserve white space, and wrap lines at break opportunities and after every space or tab.
+    BreakSpaces,
+}
+
+impl Default for WhiteSpace {
+    fn default() -> Self {
+        WhiteSpace::Normal
+    }
+}
+
+impl WhiteSpace {
+    /// Returns the line breaking class of the character, as seen under this mode.
+    fn resolve(self, c: char) -> BreakClass {
+        use WhiteSpace::*;
+        match (self, c) {
+            (Normal | Nowrap, '\t' | '\n' | '\r') | (PreLine, '\t') => SP,
+            _ => break_property(c as u32),
+        }
+    }
+}
+
+/// Returns an iterator over line break opportunities in the specified string, with white space
+/// processed according to the given CSS `white-space` mode.
+///
+/// In collapsing modes tabs, and unless the mode is [`PreLine`](WhiteSpace::PreLine) line feeds
+/// and carriage returns, are treated as spaces. As collapsing a run of spaces into one does not
+/// move the break opportunity after it, that is left to the renderer, and the byte indices
+/// returned always refer to the original string. Only mandatory breaks are returned for the
+/// modes that do not wrap, including the one at the end of the text.
+///
+/// [`WhiteSpace::PreWrap`] gives the same result as [`linebreaks`].
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{linebreaks_white_space, WhiteSpace, BreakOpportunity::*};
+/// let s = "a b\nc";
+/// let normal = linebreaks_white_space(s, WhiteSpace::Normal);
+/// assert!(normal.eq([(2, Allowed), (4, Allowed), (5, Mandatory)]));
+/// let pre = linebreaks_white_space(s, WhiteSpace::Pre);
+/// assert!(pre.eq([(4, Mandatory), (5, Mandatory)]));
+/// assert!(linebreaks_white_space(s, WhiteSpace::Nowrap).eq([(5, Mandatory)]));
+/// ```
+pub fn linebreaks_white_space(
+    s: &str,
+    mode: WhiteSpace,
+) -> impl Iterator<Item = (usize, BreakOpportunity)> + Clone + '_ {
+    let wraps = !matches!(mode, WhiteSpace::Pre | WhiteSpace::Nowrap);
+    let breaks = break_opportunities(
+        s.char_indices().map(move |(i, c)| (i, mode.resolve(c))),
+        s.len(),
+    )
+    .filter(move |&(_, opportunity)| wraps || opportunity == BreakOpportunity::Mandatory);
+    let spaces = s
+        .char_indices()
+        .filter(move |&(_, c)| mode == WhiteSpace::BreakSpaces && matches!(c, ' ' | '\t'))
+        .map(|(i, c)| (i + c.len_utf8(), BreakOpportunity::Allowed));
+
+    Merge {
+        a: breaks.peekable(),
+        b: spaces.peekable(),
+    }
+}
+
+/// Merges two iterators of break opportunities sorted by index.
+///
+/// Of two opportunities at the same index, only the one from `a` is kept.
+#[derive(Clone)]
+pub(crate) struct Merge<T, A, B>
+where
+    A: Iterator<Item = (usize, T)>,
+    B: Iterator<Item = (usize, T)>,
+{
+    pub(crate) a: Peekable<A>,
+    pub(crate) b: Peekable<B>,
+}
+
+impl<T, A, B> Iterator for Merge<T, A, B>
+where
+    A: Iterator<Item = (usize, T)>,
+    B: Iterator<Item = (usize, T)>,
+{
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.a.peek(), self.b.peek()) {
+            (Some(x), Some(y)) if y.0 < x.0 => self.b.next(),
+            (Some(x), Some(y)) if y.0 == x.0 => {
+                self.b.next();
+                self.a.next()
+            }
+            (Some(_), _) => self.a.next(),
+            (None, _) => self.b.next(),
+        }
+    }
+}
+
+/// Terminator of a line, ending it with a mandatory break.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum LineTerminator {
+    /// Line feed (LF)
+    LineFeed,
+    /// Carriage return (CR) not followed by a line feed
+    CarriageReturn,
+    /// Carriage return followed by line feed (CR LF)
+    CrLf,
+    /// Next line (NEL)
+    NextLine,
+    /// Line tabulation, form feed, line separator or paragraph separator (BK)
+    Mandatory,
+}
+
+/// Returns an iterator over the lines of the string, split at mandatory breaks.
+///
+/// Lines are given as tuples of the line, excluding its terminator, and the terminator, which is
+/// only `None` for a final line without one. As with [`str::lines`], no empty line follows a
+/// final terminator. Unlike [`linebreaks`], only the rules for mandatory breaks (LB4 to LB6) are
+/// applied, which is considerably cheaper, while still ending lines exactly at the
+/// [`Mandatory`](BreakOpportunity::Mandatory) break opportunities it returns.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{hard_lines, LineTerminator::*};
+/// let s = "one\r\ntwo\u{2028}three";
+/// assert!(hard_lines(s).eq([("one", Some(CrLf)), ("two", Some(Mandatory)), ("three", None)]));
+/// ```
+pub fn hard_lines(s: &str) -> impl Iterator<Item = (&str, Option<LineTerminator>)> + Clone {
+    use LineTerminator::*;
+
+    let mut rest = s;
+    from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let terminator = rest.char_indices().find_map(|(i, c)| {
+            let terminator = match c {
+                '\n' => LineFeed,
+                '\r' if rest[i + 1..].starts_with('\n') => return Some((i, 2, CrLf)),
+                '\r' => CarriageReturn,
+                '\u{85}' => NextLine,
+                '\u{B}' | '\u{C}' | '\u{2028}' | '\u{2029}' => Mandatory,
+                _ => return None,
+            };
+            Some((i, c.len_utf8(), terminator))
+        });
+        let (line, terminator) = match terminator {
+            Some((i, len, terminator)) => {
+                let line = &rest[..i];
+                rest = &rest[i + len..];
+                (line, Some(terminator))
+            }
+            None => (core::mem::take(&mut rest), None),
+        };
+        Some((line, terminator))
+    })
 }
 
 /// Divides the string at the last index where further breaks do not depend on prior context.
 ///
-/// The trivial index at `eot` is excluded.
+/// The trivial index at `eot` is excluded. Whether there is a break opportunity at the returned
+/// index itself does still depend on the preceding text.
 ///
 /// A common optimization is to determine only the nearest line break opportunity before the first
 /// character that would cause the line to become overfull, requiring backward traversal, of which
 /// there are two approaches:
 ///
-/// * Cache breaks from forward traversals
+/// * Cache breaks from forward traversals, e.g. with `BreakCache`
 /// * Step backward and with `split_at_safe` find a pos to safely search forward from, repeatedly
 ///
 /// # Examples
@@ -131,24 +828,232 @@ pub fn linebreaks(s: &str) -> impl Iterator<Item = (usize, BreakOpportunity)> +
 /// let s = "Not allowed to break within em dashes: — —";
 /// let (prev, safe) = split_at_safe(s);
 /// let n = prev.len();
-/// assert!(linebreaks(safe).eq(linebreaks(s).filter_map(|(i, x)| i.checked_sub(n).map(|i| (i, x)))));
+/// let breaks = linebreaks(s).filter(|&(i, _)| i > n).map(|(i, x)| (i - n, x));
+/// assert!(linebreaks(safe).eq(breaks));
 /// ```
 pub fn split_at_safe(s: &str) -> (&str, &str) {
-    let mut chars = s.char_indices().rev().scan(None, |state, (i, c)| {
+    s.split_at(safe_point_before(s, s.len(), s.len()).unwrap_or(0))
+}
+
+/// Returns the nearest byte index at or before `idx` where further breaks do not depend on prior
+/// context, looking back at most `max_lookback` bytes.
+///
+/// Such an index is the start of a pair of characters, as found by [`split_at_safe`], or the
+/// start of the text. Unlike [`split_at_safe`], the cost is bounded even on pathological inputs
+/// without any safe pairs, such as a long run of spaces after an opening punctuation mark, in
+/// which case `None` is returned.
+///
+/// # Panics
+///
+/// Panics if `idx` is out of bounds or not on a char boundary.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::safe_point_before;
+/// let s = "Not allowed to break within em dashes: — —";
+/// assert_eq!(safe_point_before(s, 30, 10), Some(29));
+/// let s = format!("a ({}b", " ".repeat(100));
+/// assert_eq!(safe_point_before(&s, 100, 50), None);
+/// assert_eq!(safe_point_before(&s, 100, 100), Some(2));
+/// ```
+pub fn safe_point_before(s: &str, idx: usize, max_lookback: usize) -> Option<usize> {
+    let end = idx + s[idx..].chars().next().map_or(0, char::len_utf8);
+    let mut next = s[end..].chars().next().map(|c| break_property(c as u32));
+    for (i, c) in s[..end].char_indices().rev() {
+        if idx - i > max_lookback {
+            return None;
+        }
         let cls = break_property(c as u32);
-        let is_safe_pair = state
-            .replace(cls)
-            .map_or(false, |prev| is_safe_pair(cls, prev)); // Reversed since iterating backwards
-        Some((i, is_safe_pair))
-    });
-    chars.find(|&(_, is_safe_pair)| is_safe_pair);
-    // Include preceding char for `linebreaks` to pick up break before match (disallowed after sot)
-    s.split_at(chars.next().map_or(0, |(i, _)| i))
+        // Split before the pair, as breaks after its first char do not depend on what precedes it
+        if next.map_or(false, |next| is_safe_pair(cls, next)) {
+            return Some(i);
+        }
+        next = Some(cls);
+    }
+    Some(0)
+}
+
+/// Updates the line break opportunities of a string after an edit.
+///
+/// `breaks` are the opportunities of the text before the edit, as returned by [`linebreaks`],
+/// and `s` is the text after replacing the byte range `edit` of it with `new_len` bytes. Only
+/// the region between the nearest safe pairs, as found by [`split_at_safe`], before and after
+/// the edit is processed again, past which the old breaks are shifted into place.
+///
+/// Returns the range of entries in `breaks` that were recomputed.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{linebreaks, update_linebreaks};
+/// let mut breaks: Vec<_> = linebreaks("Hello world! How are you?").collect();
+/// let s = "Hello, world! How are you?";
+/// update_linebreaks(&mut breaks, s, 5..5, 1);
+/// assert!(breaks.into_iter().eq(linebreaks(s)));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn update_linebreaks(
+    breaks: &mut alloc::vec::Vec<(usize, BreakOpportunity)>,
+    s: &str,
+    edit: core::ops::Range<usize>,
+    new_len: usize,
+) -> core::ops::Range<usize> {
+    let edit_end = edit.start + new_len;
+    let start = split_at_safe(&s[..edit.start]).0.len();
+    // Breaks after the first char of a safe pair past the edit are only shifted
+    let mut end = s.len();
+    let mut chars = s[edit_end..]
+        .char_indices()
+        .map(|(i, c)| (edit_end + i, break_property(c as u32)));
+    if let Some((mut prev_i, mut prev)) = chars.next() {
+        for (i, cls) in chars {
+            if is_safe_pair(prev, cls) {
+                end = prev_i;
+                break;
+            }
+            prev_i = i;
+            prev = cls;
+        }
+    }
+
+    // The break at the start of the region, if any, precedes the edit and is kept
+    let lo = if start == 0 {
+        0
+    } else {
+        breaks.partition_point(|&(i, _)| i <= start)
+    };
+    let hi = breaks.partition_point(|&(i, _)| i <= end - edit_end + edit.end);
+    for (i, _) in &mut breaks[hi..] {
+        *i = *i - edit.end + edit_end;
+    }
+    let len = breaks.len() - (hi - lo);
+    breaks.splice(
+        lo..hi,
+        linebreaks(&s[start..])
+            .map(|(i, opportunity)| (start + i, opportunity))
+            .take_while(|&(i, _)| i <= end),
+    );
+    lo..lo + breaks.len() - len
+}
+
+/// Cache of the line break opportunities in a string, found lazily on demand.
+///
+/// The string is only processed as far as needed to answer each query, after which lookups
+/// are binary searches in the sorted list of opportunities found so far. This makes it cheap to
+/// step backward from the first character that would cause a line to become overfull.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{BreakCache, BreakOpportunity::{Mandatory, Allowed}};
+/// let mut cache = BreakCache::new("Hello world! How are you?");
+/// assert_eq!(cache.last_before(10), Some((6, Allowed)));
+/// assert_eq!(cache.first_after(6), Some((13, Allowed)));
+/// assert_eq!(cache.range(0..17), [(6, Allowed), (13, Allowed)]);
+/// assert_eq!(cache.first_after(21), Some((25, Mandatory)));
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct BreakCache<'a> {
+    breaks: alloc::vec::Vec<(usize, BreakOpportunity)>,
+    linebreaks: Linebreaks<'a>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> BreakCache<'a> {
+    /// Creates an empty cache for the specified string.
+    pub fn new(s: &'a str) -> Self {
+        Self {
+            breaks: alloc::vec::Vec::new(),
+            linebreaks: Linebreaks::new(s),
+        }
+    }
+
+    /// Finds all break opportunities at or before the byte index.
+    fn extend_to(&mut self, idx: usize) {
+        while self.linebreaks.index() <= idx && self.linebreaks.state.is_some() {
+            match self.linebreaks.next() {
+                Some(x) => self.breaks.push(x),
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the last break opportunity at or before the byte index, if any.
+    pub fn last_before(&mut self, idx: usize) -> Option<(usize, BreakOpportunity)> {
+        self.extend_to(idx);
+        let j = self.breaks.partition_point(|&(i, _)| i <= idx);
+        j.checked_sub(1).map(|j| self.breaks[j])
+    }
+
+    /// Returns the first break opportunity strictly after the byte index, if any.
+    pub fn first_after(&mut self, idx: usize) -> Option<(usize, BreakOpportunity)> {
+        self.extend_to(idx);
+        let j = self.breaks.partition_point(|&(i, _)| i <= idx);
+        if j == self.breaks.len() {
+            let x = self.linebreaks.next()?;
+            self.breaks.push(x);
+        }
+        Some(self.breaks[j])
+    }
+
+    /// Returns the break opportunities within the range of byte indices.
+    pub fn range(&mut self, range: core::ops::Range<usize>) -> &[(usize, BreakOpportunity)] {
+        self.extend_to(range.end);
+        let start = self.breaks.partition_point(|&(i, _)| i < range.start);
+        let end = self.breaks.partition_point(|&(i, _)| i < range.end);
+        &self.breaks[start..end.max(start)]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    extern crate std;
+
+    /// Text exercising many rules of the pair table, to compare other ways of finding breaks with.
+    const TEXT: &str = "a—b ( \"c\" )\r\n中文 \u{200D}👍🏽 1.5%-x\u{301}  — —\n";
+
+    /// Returns a pseudo-random concatenation of `len` pieces of text that are interesting to break.
+    fn random_text(seed: u64, len: usize) -> std::string::String {
+        const PIECES: [&str; 20] = [
+            "a",
+            "1",
+            " ",
+            "\n",
+            "\r",
+            "(",
+            ")",
+            "\"",
+            "—",
+            "-",
+            "%",
+            ".",
+            "中",
+            "ก",
+            "\u{301}",
+            "\u{200D}",
+            "\u{2060}",
+            "\u{3000}",
+            "👍",
+            "\u{1F3FD}",
+        ];
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                // Linear congruential generator of Knuth's MMIX
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                PIECES[(state >> 33) as usize % PIECES.len()]
+            })
+            .collect()
+    }
 
     #[test]
     fn it_works() {
@@ -156,5 +1061,228 @@ mod tests {
         assert_eq!(break_property(0xDB80), BreakClass::Surrogate);
         assert_eq!(break_property(0xe01ef), BreakClass::CombiningMark);
         assert_eq!(break_property(0x10ffff), BreakClass::Unknown);
+        for b in 0..0x80 {
+            assert_eq!(ASCII_BREAK_PROP[b as usize], break_property(b));
+        }
+        assert_eq!(east_asian_width(0x3400), EastAsianWidth::Wide);
+        assert_eq!(east_asian_width(0x1F600), EastAsianWidth::Wide);
+        assert_eq!(east_asian_width(0x10fffd), EastAsianWidth::Ambiguous);
+    }
+
+    #[test]
+    fn column_width_of_clusters() {
+        assert_eq!(column_width("กิ"), 1);
+        assert_eq!(column_width("\u{1000}\u{102D}"), 1);
+        assert_eq!(column_width("👍🏽"), 2);
+        assert_eq!(column_width("👩\u{200D}"), 2);
+        assert_eq!(column_width("\u{1100}\u{1161}\u{11A8}"), 2);
+        assert_eq!(column_width("a\u{200B}\u{2060}\tb"), 2);
+    }
+
+    #[test]
+    fn break_properties_match_break_property() {
+        let s = "Mixed ASCII and non-ASCII: ½ — 中文, plus emoji 👍🏽!";
+        let mut out = [BreakClass::Unknown; 64];
+        let n = break_properties_into(s, &mut out);
+        assert!(out[..n].iter().copied().eq(break_properties(s)));
+        assert!(break_properties(s).eq(s.chars().map(|c| break_property(c as u32))));
+
+        // Every ASCII character, in runs long enough to be classified 16 at a time
+        let s: std::string::String = (0..4 * 128).map(|i| (i * 37 % 128) as u8 as char).collect();
+        let s = s.clone() + "中" + &s[..40];
+        let mut out = std::vec![BreakClass::Unknown; s.len()];
+        let n = break_properties_into(&s, &mut out);
+        assert!(out[..n].iter().copied().eq(break_properties(&s)));
+        // Buffers exactly fitting the characters
+        let n = break_properties_into(&s, &mut out[..s.chars().count()]);
+        assert!(out[..n].iter().copied().eq(break_properties(&s)));
+    }
+
+    #[test]
+    fn hard_lines_match_mandatory_breaks() {
+        use LineTerminator::*;
+        let s = "a\nb\rc\r\n\r\nd\u{85}e\u{B}f\u{C}g\u{2028}h\u{2029}";
+        assert!(linebreaks(s)
+            .filter(|&(_, x)| x == BreakOpportunity::Mandatory)
+            .map(|(i, _)| i)
+            .eq([2, 4, 7, 9, 12, 14, 16, 20, 24]));
+        assert!(hard_lines(s)
+            .map(|(line, _)| line)
+            .eq(["a", "b", "c", "", "d", "e", "f", "g", "h"]));
+        assert!(hard_lines(s).map(|(_, x)| x.unwrap()).eq([
+            LineFeed,
+            CarriageReturn,
+            CrLf,
+            CrLf,
+            NextLine,
+            Mandatory,
+            Mandatory,
+            Mandatory,
+            Mandatory
+        ]));
+        assert!(hard_lines("a").eq([("a", None)]));
+    }
+
+    #[test]
+    fn split_at_safe_keeps_breaks() {
+        // The break before "a" is disallowed after an opening punctuation mark and spaces
+        for s in ["(  ab", "Not allowed to break within em dashes: — —"] {
+            let (prev, safe) = split_at_safe(s);
+            let n = prev.len();
+            let breaks = linebreaks(s)
+                .filter(|&(i, _)| i > n)
+                .map(|(i, x)| (i - n, x));
+            assert!(linebreaks(safe).eq(breaks), "{:?}", s);
+        }
+        assert_eq!(split_at_safe("(  ab"), ("(  ", "ab"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_linebreaks_matches_sequential() {
+        use rayon::iter::ParallelIterator;
+        use std::vec::Vec;
+
+        let texts = (0..16).map(|seed| random_text(seed, 200));
+        for s in Some(TEXT.repeat(50)).into_iter().chain(texts) {
+            for min_chunk_len in [1, 2, 7, 64] {
+                let breaks: Vec<_> = par_linebreaks_chunked(&s, min_chunk_len).collect();
+                assert!(breaks.into_iter().eq(linebreaks(&s)), "{:?}", s);
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn update_linebreaks_matches_full() {
+        use alloc::{string::String, vec::Vec};
+
+        let check = |old: &str, edit: core::ops::Range<usize>, insert: &str| {
+            let mut s = String::from(old);
+            s.replace_range(edit.clone(), insert);
+            let mut breaks: Vec<_> = linebreaks(old).collect();
+            let changed = update_linebreaks(&mut breaks, &s, edit.clone(), insert.len());
+            assert!(
+                breaks.iter().copied().eq(linebreaks(&s)),
+                "{:?} edit {:?}",
+                old,
+                edit
+            );
+            assert!(changed.end <= breaks.len());
+        };
+        let edits = [
+            (0..0, "x"),
+            (1..4, ""),
+            (5..6, "(\u{2060} "),
+            (15..18, "\n"),
+            (21..TEXT.len(), ". "),
+            (0..TEXT.len(), ""),
+        ];
+        for (edit, insert) in edits {
+            check(TEXT, edit, insert);
+        }
+        for seed in 0..64 {
+            let old = random_text(seed, 40);
+            let boundaries: Vec<_> = (0..=old.len())
+                .filter(|&i| old.is_char_boundary(i))
+                .collect();
+            let a = boundaries[seed as usize * 7 % boundaries.len()];
+            let b = boundaries[seed as usize * 13 % boundaries.len()];
+            let insert = random_text(seed + 64, seed as usize % 4);
+            check(&old, a.min(b)..a.max(b), &insert);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn break_cache_matches_linebreaks() {
+        use alloc::vec::Vec;
+
+        let texts = (0..16).map(|seed| random_text(seed, 30));
+        for s in Some(TEXT.into()).into_iter().chain(texts) {
+            let s = &s[..];
+            let breaks: Vec<_> = linebreaks(s).collect();
+            // Query out of order to exercise extending the cache
+            for idx in (0..=s.len() + 1).rev().step_by(3).chain(0..=s.len() + 1) {
+                let mut cache = BreakCache::new(s);
+                let first_after = breaks.iter().copied().find(|&(i, _)| i > idx);
+                assert_eq!(cache.first_after(idx), first_after, "{:?} at {}", s, idx);
+                let last_before = breaks.iter().copied().rev().find(|&(i, _)| i <= idx);
+                assert_eq!(cache.last_before(idx), last_before, "{:?} at {}", s, idx);
+                assert_eq!(
+                    cache.range(idx / 2..idx),
+                    breaks
+                        .iter()
+                        .copied()
+                        .filter(|&(i, _)| (idx / 2..idx).contains(&i))
+                        .collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn safe_point_before_is_safe() {
+        let texts = (0..16).map(|seed| random_text(seed, 30));
+        for s in Some(TEXT.into()).into_iter().chain(texts) {
+            let s = &s[..];
+            for idx in (0..=s.len()).filter(|&i| s.is_char_boundary(i)) {
+                let p = safe_point_before(s, idx, s.len()).unwrap();
+                assert!(p <= idx);
+                let breaks = linebreaks(s).filter(|&(i, _)| i > p);
+                let safe_breaks = linebreaks(&s[p..]).map(|(i, x)| (p + i, x));
+                assert!(safe_breaks.eq(breaks), "{:?} at {}", s, idx);
+                assert_eq!(safe_point_before(s, idx, idx - p), Some(p));
+                if 0 < p && p < idx {
+                    assert_eq!(safe_point_before(s, idx, idx - p - 1), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linebreaks_into_matches_iterator() {
+        let s = "a—b 中文\r\nc\u{200D}d";
+        let mut out = [BreakStatus::NoBreak; 20];
+        linebreaks_into(s, &mut out);
+        let mut breaks = linebreaks(s).peekable();
+        for (i, &status) in out.iter().enumerate() {
+            let expected = breaks.next_if(|&(j, _)| i == j).map(|(_, x)| x);
+            assert_eq!(status, BreakStatus::from(expected), "at {}", i);
+        }
+    }
+
+    #[test]
+    fn segments_cover_string() {
+        let s = "a \u{200B}b\r\n\r\nc—d ";
+        let mut end = 0;
+        for segment in segments(s) {
+            assert_eq!(segment.text.as_ptr(), s[end..].as_ptr());
+            end += segment.text.len() + segment.trailing_whitespace.len();
+        }
+        assert_eq!(end, s.len());
+        assert!(segments(s)
+            .map(|x| x.text)
+            .eq(["a", "b", "", "c", "—", "d"]));
+    }
+
+    #[test]
+    fn white_space_modes() {
+        use BreakOpportunity::{Allowed, Mandatory};
+        let s = "a  b\tc\r\nd ";
+        assert!(linebreaks_white_space(s, WhiteSpace::PreWrap).eq(linebreaks(s)));
+        assert!(linebreaks_white_space(s, WhiteSpace::PreLine).eq([
+            (3, Allowed),
+            (5, Allowed),
+            (8, Mandatory),
+            (10, Mandatory)
+        ]));
+        assert!(linebreaks_white_space(s, WhiteSpace::BreakSpaces).eq([
+            (2, Allowed),
+            (3, Allowed),
+            (5, Allowed),
+            (8, Mandatory),
+            (10, Mandatory)
+        ]));
     }
 }
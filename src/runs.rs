@@ -0,0 +1,138 @@
+//! Line breaking of attributed text, consisting of runs with differing options.
+//!
+//! Breaking each run separately with [`linebreaks`](crate::linebreaks) would lose the context at
+//! run boundaries, e.g. wrongly allow a break between a bold word and a following plain comma.
+
+use crate::{break_opportunities, break_property, BreakClass, BreakOpportunity};
+
+/// Strictness of line breaking, as with the CSS `line-break` property.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Strictness {
+    /// Also allow breaks before nonstarters (NS), such as iteration marks and centered
+    /// punctuation.
+    Loose,
+    /// Allow breaks before conditional Japanese starters (CJ), such as small kana.
+    Normal,
+    /// Treat conditional Japanese starters (CJ) as nonstarters (NS), as done by
+    /// [`linebreaks`](crate::linebreaks).
+    Strict,
+}
+
+impl Default for Strictness {
+    fn default() -> Self {
+        Strictness::Strict
+    }
+}
+
+/// Options applying to a single run of text.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct RunOptions<'a> {
+    /// The strictness of line breaking.
+    pub strictness: Strictness,
+    /// The language of the text as a [BCP 47](https://www.rfc-editor.org/info/bcp47) tag, e.g.
+    /// `"ja-JP"`.
+    ///
+    /// Ambiguous (AI) characters are resolved to ideographic (ID) in Chinese, Japanese and
+    /// Korean text, and to alphabetic (AL) otherwise.
+    pub language: Option<&'a str>,
+}
+
+impl RunOptions<'_> {
+    /// Returns whether the language is one with East Asian typographic conventions.
+    fn is_east_asian(&self) -> bool {
+        self.language.map_or(false, |language| {
+            let primary = language.split(|c| c == '-' || c == '_').next().unwrap();
+            ["zh", "ja", "ko"]
+                .iter()
+                .any(|x| primary.eq_ignore_ascii_case(x))
+        })
+    }
+
+    /// Resolves the line breaking class of a character in the run, as allowed by LB1.
+    fn resolve(&self, cls: BreakClass) -> BreakClass {
+        use BreakClass::*;
+        match (cls, self.strictness) {
+            (ConditionalJapaneseStarter, Strictness::Loose | Strictness::Normal) => Ideographic,
+            (NonStarter, Strictness::Loose) => Ideographic,
+            (Ambiguous, _) if self.is_east_asian() => Ideographic,
+            _ => cls,
+        }
+    }
+}
+
+/// Returns an iterator over line break opportunities in the text consisting of the specified
+/// runs.
+///
+/// Break opportunities are given as tuples of the position of the character succeeding the break,
+/// as the index of its run and the byte index within the run, and the type. A break between two
+/// runs is thus reported at the start of the latter, while the mandatory break at the end of the
+/// text is reported at the end of the last run.
+///
+/// The text is broken as a whole, with the options of each run applied to its characters.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{runs::*, BreakOpportunity::{Mandatory, Allowed}};
+/// let ja = RunOptions { strictness: Strictness::Normal, language: Some("ja") };
+/// let runs = [("Hello", RunOptions::default()), (", ", RunOptions::default()), ("ぁぃ", ja)];
+/// assert!(linebreaks_runs(runs).eq([((2, 0), Allowed), ((2, 3), Allowed), ((2, 6), Mandatory)]));
+/// ```
+pub fn linebreaks_runs<'a, I>(
+    runs: I,
+) -> impl Iterator<Item = ((usize, usize), BreakOpportunity)> + Clone + 'a
+where
+    I: IntoIterator<Item = (&'a str, RunOptions<'a>)>,
+    I::IntoIter: Clone + 'a,
+{
+    let runs = runs.into_iter().enumerate();
+    let end = runs
+        .clone()
+        .last()
+        .map_or((0, 0), |(run, (s, _))| (run, s.len()));
+    let classes = runs.flat_map(|(run, (s, options))| {
+        s.char_indices()
+            .map(move |(i, c)| ((run, i), options.resolve(break_property(c as u32))))
+    });
+    break_opportunities(classes, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BreakOpportunity::{Allowed, Mandatory};
+
+    #[test]
+    fn context_across_runs() {
+        let options = RunOptions::default();
+        let runs = [
+            ("a", options),
+            ("", options),
+            (" b", options),
+            ("", options),
+        ];
+        assert!(linebreaks_runs(runs).eq([((2, 1), Allowed), ((3, 0), Mandatory)]));
+        assert!(linebreaks_runs([("", options)]).eq([]));
+    }
+
+    #[test]
+    fn strictness_and_language() {
+        let mut options = RunOptions::default();
+        // Iteration mark after ideograph, and ambiguous circled digit after letter
+        let s = "字々a①";
+        assert!(linebreaks_runs([(s, options)]).eq([((0, 6), Allowed), ((0, 10), Mandatory)]));
+        options.strictness = Strictness::Loose;
+        assert!(linebreaks_runs([(s, options)]).eq([
+            ((0, 3), Allowed),
+            ((0, 6), Allowed),
+            ((0, 10), Mandatory)
+        ]));
+        options.language = Some("zh-Hant");
+        assert!(linebreaks_runs([(s, options)]).eq([
+            ((0, 3), Allowed),
+            ((0, 6), Allowed),
+            ((0, 7), Allowed),
+            ((0, 10), Mandatory)
+        ]));
+    }
+}
@@ -108,6 +108,49 @@ use BreakClass::{
     ZeroWidthJoiner as ZWJ, ZeroWidthSpace as ZW,
 };
 
+/// East Asian Width property of a character, as defined in
+/// [Unicode Standard Annex #11](https://www.unicode.org/reports/tr11/).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
+pub enum EastAsianWidth {
+    /// Wide in East Asian contexts and narrow elsewhere
+    Ambiguous,
+    /// Wide compatibility variant of a narrow character
+    Fullwidth,
+    /// Narrow compatibility variant of a wide character
+    Halfwidth,
+    /// Does not occur in legacy East Asian character sets
+    Neutral,
+    /// Narrow counterpart of a fullwidth character
+    Narrow,
+    /// Always wide
+    Wide,
+}
+
+/// Grapheme_Cluster_Break property of a character, as defined in
+/// [Unicode Standard Annex #29](https://www.unicode.org/reports/tr29/).
+///
+/// Extended_Pictographic characters, which are all of the value Other, have a value of their own.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
+pub(crate) enum GraphemeClusterBreak {
+    Other,
+    CarriageReturn,
+    LineFeed,
+    Control,
+    Extend,
+    ZeroWidthJoiner,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    HangulLJamo,
+    HangulVJamo,
+    HangulTJamo,
+    HangulLvSyllable,
+    HangulLvtSyllable,
+    ExtendedPictographic,
+}
+
 /// Ceiling for code points in the Basic Multilingual Place (BMP).
 const BMP_LIMIT: u32 = 0x10000;
 
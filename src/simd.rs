@@ -0,0 +1,80 @@
+//! Classification of runs of ASCII characters 16 at a time with SSSE3.
+//!
+//! Support for SSSE3 is detected at runtime, unless enabled at compile time.
+
+use crate::{BreakClass, ASCII_BREAK_PROP};
+use core::arch::x86_64::*;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const LANES: usize = 16;
+
+/// Whether SSSE3 is supported, once detected: 0 if not yet known, 1 if not and 2 if so.
+static HAS_SSSE3: AtomicU8 = AtomicU8::new(0);
+
+#[inline]
+fn has_ssse3() -> bool {
+    if cfg!(target_feature = "ssse3") {
+        return true;
+    }
+    match HAS_SSSE3.load(Ordering::Relaxed) {
+        0 => {
+            // SAFETY: CPUID is available on all x86-64 CPUs. Only unsafe in older Rust versions.
+            #[allow(unused_unsafe)]
+            let ecx = unsafe { __cpuid(1) }.ecx;
+            let has_ssse3 = ecx & 1 << 9 != 0;
+            HAS_SSSE3.store(1 + has_ssse3 as u8, Ordering::Relaxed);
+            has_ssse3
+        }
+        x => x == 2,
+    }
+}
+
+/// Writes the line breaking classes of the longest prefix of the bytes that is made up of whole
+/// chunks of 16 ASCII characters, and fits in the buffer, to the buffer, returning its length.
+///
+/// Returns 0 if SSSE3 is not supported.
+#[inline]
+pub(crate) fn classify_ascii(bytes: &[u8], out: &mut [BreakClass]) -> usize {
+    // Checked up front, as runs of ASCII characters are often short in other text
+    let starts_with_chunk = bytes.get(..LANES).map_or(false, |chunk| {
+        let word = u128::from_ne_bytes(chunk.try_into().unwrap());
+        word & 0x8080_8080_8080_8080_8080_8080_8080_8080 == 0
+    });
+    if out.len() < LANES || !starts_with_chunk || !has_ssse3() {
+        return 0;
+    }
+    // SAFETY: SSSE3 is supported
+    unsafe { classify_ascii_ssse3(bytes, out) }
+}
+
+/// # Safety
+///
+/// The CPU must support SSSE3.
+#[target_feature(enable = "ssse3")]
+unsafe fn classify_ascii_ssse3(bytes: &[u8], out: &mut [BreakClass]) -> usize {
+    // The table split into eight vectors, each with the classes of 16 consecutive characters
+    let mut table = [_mm_setzero_si128(); 128 / LANES];
+    for (x, classes) in table.iter_mut().zip(ASCII_BREAK_PROP.chunks_exact(LANES)) {
+        *x = _mm_loadu_si128(classes.as_ptr() as *const __m128i);
+    }
+
+    let mut n = 0;
+    while n + LANES <= bytes.len().min(out.len()) {
+        let chars = _mm_loadu_si128(bytes[n..].as_ptr() as *const __m128i);
+        if _mm_movemask_epi8(chars) != 0 {
+            break; // Not all ASCII
+        }
+        let mut classes = _mm_setzero_si128();
+        for (i, &x) in table.iter().enumerate() {
+            // Indices of characters in this part of the table get the high bit cleared, and
+            // others, which saturate, set so that the shuffle gives zero for them
+            let offset = _mm_sub_epi8(chars, _mm_set1_epi8((i * LANES) as i8));
+            let indices = _mm_adds_epu8(offset, _mm_set1_epi8(0x70));
+            classes = _mm_or_si128(classes, _mm_shuffle_epi8(x, indices));
+        }
+        // SAFETY: The bytes are the valid BreakClass values of the table
+        _mm_storeu_si128(out[n..].as_mut_ptr() as *mut __m128i, classes);
+        n += LANES;
+    }
+    n
+}
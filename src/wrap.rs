@@ -0,0 +1,944 @@
+//! Wrapping of text into lines that fit within a given width.
+//!
+//! Widths are measured by a caller-supplied function, and may be anything from numbers of
+//! terminal columns, as given by [`column_width`](crate::column_width), to font units. Trailing
+//! whitespace and line terminators are excluded when measuring a line, as they are allowed to
+//! hang past its end.
+
+use crate::{
+    break_property, grapheme_cluster_break, linebreaks, split_at_safe, BreakClass,
+    BreakOpportunity, GraphemeClusterBreak,
+};
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+    iter::{from_fn, once},
+    ops::{Add, Range},
+};
+
+/// A width as returned by measure functions.
+pub trait Width: Copy + PartialOrd + Add<Output = Self> {
+    /// Converts the width to a floating-point number, for computing the cost of lines.
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_width {
+    ($($t:ty)*) => {$(
+        impl Width for $t {
+            #[inline]
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )*};
+}
+
+impl_width!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize f32 f64);
+
+/// A type of break opportunity that lines can be wrapped at.
+pub trait Break: Copy {
+    /// Returns whether a line must end at the break.
+    fn is_mandatory(self) -> bool;
+}
+
+impl Break for BreakOpportunity {
+    #[inline]
+    fn is_mandatory(self) -> bool {
+        self == BreakOpportunity::Mandatory
+    }
+}
+
+/// Returns whether the character is whitespace or a line terminator, allowed to hang past the end
+/// of a line.
+fn is_trailing_whitespace(c: char) -> bool {
+    use BreakClass::*;
+    matches!(
+        break_property(c as u32),
+        Space | Mandatory | CarriageReturn | LineFeed | NextLine | ZeroWidthSpace
+    )
+}
+
+/// Returns the line with trailing whitespace and line terminators removed.
+pub(crate) fn trim_trailing_whitespace(line: &str) -> &str {
+    line.trim_end_matches(is_trailing_whitespace)
+}
+
+/// Returns whether there is an extended grapheme cluster boundary between the two characters.
+///
+/// `is_odd_ri` tells whether `a` ends an odd-length run of regional indicators, and
+/// `is_emoji_zwj` whether `a` is a ZWJ preceded by an Extended_Pictographic character and any
+/// number of Extend characters.
+fn is_grapheme_boundary(
+    a: GraphemeClusterBreak,
+    b: GraphemeClusterBreak,
+    is_odd_ri: bool,
+    is_emoji_zwj: bool,
+) -> bool {
+    use GraphemeClusterBreak::*;
+    match (a, b) {
+        (CarriageReturn, LineFeed) => false, // GB3
+        (Control | CarriageReturn | LineFeed, _) | (_, Control | CarriageReturn | LineFeed) => {
+            true // GB4, GB5
+        }
+        (HangulLJamo, HangulLJamo | HangulVJamo | HangulLvSyllable | HangulLvtSyllable)
+        | (HangulVJamo | HangulLvSyllable, HangulVJamo | HangulTJamo)
+        | (HangulTJamo | HangulLvtSyllable, HangulTJamo) => false, // GB6, GB7, GB8
+        (_, Extend | ZeroWidthJoiner | SpacingMark) | (Prepend, _) => false, // GB9, GB9a, GB9b
+        (ZeroWidthJoiner, ExtendedPictographic) => !is_emoji_zwj,            // GB11
+        (RegionalIndicator, RegionalIndicator) => !is_odd_ri,                // GB12, GB13
+        _ => true,                                                           // GB999
+    }
+}
+
+/// Returns an iterator over the byte indices of the ends of the extended grapheme clusters in
+/// the string, as defined in [UAX #29](https://www.unicode.org/reports/tr29/).
+pub(crate) fn grapheme_boundaries(s: &str) -> impl Iterator<Item = usize> + '_ {
+    use GraphemeClusterBreak::{Extend, ExtendedPictographic, RegionalIndicator, ZeroWidthJoiner};
+    s.char_indices()
+        .map(|(i, c)| (i, Some(grapheme_cluster_break(c as u32))))
+        .chain(once((s.len(), None)))
+        // Whether the text so far ends with an Extended_Pictographic character and any Extend
+        // characters, or additionally a ZWJ
+        .scan(
+            (None, false, false, false),
+            |(prev, is_odd_ri, is_emoji, is_emoji_zwj), (i, cls)| {
+                let is_boundary = match (*prev, cls) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(a), Some(b)) => is_grapheme_boundary(a, b, *is_odd_ri, *is_emoji_zwj),
+                };
+                *is_odd_ri = cls == Some(RegionalIndicator) && !*is_odd_ri;
+                *is_emoji_zwj = cls == Some(ZeroWidthJoiner) && *is_emoji;
+                *is_emoji = cls == Some(ExtendedPictographic) || cls == Some(Extend) && *is_emoji;
+                *prev = cls;
+                Some((i, is_boundary))
+            },
+        )
+        .filter_map(|(i, is_boundary)| if is_boundary { Some(i) } else { None })
+}
+
+/// How a line returned by [`wrap`] ends.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LineEnd {
+    /// The line ends at a break opportunity.
+    Break(BreakOpportunity),
+    /// The line only ends here because the text would overflow otherwise.
+    Emergency,
+}
+
+/// Options for [`wrap`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Options<'a> {
+    /// Whether to break text between break opportunities that does not fit on a line of its own
+    /// at extended grapheme cluster boundaries, as with the CSS `overflow-wrap: anywhere`.
+    ///
+    /// Such breaks are reported as [`LineEnd::Emergency`].
+    pub break_anywhere: bool,
+    /// Indentation of the first line, placed after the prefix.
+    pub initial_indent: &'a str,
+    /// Indentation of all lines but the first, placed after the prefix.
+    pub subsequent_indent: &'a str,
+    /// Prefix of every line, e.g. `"// "` or `"> "`.
+    pub prefix: &'a str,
+}
+
+impl<'a> Options<'a> {
+    /// Returns the prefix and indentation to insert before the line starting at the byte index.
+    fn line_prefix(&self, line_start: usize) -> (&'a str, &'a str) {
+        let indent = if line_start == 0 {
+            self.initial_indent
+        } else {
+            self.subsequent_indent
+        };
+        (self.prefix, indent)
+    }
+}
+
+/// Wraps the string by placing each break as late as possible.
+///
+/// Returns an iterator over the byte ranges of the lines, each paired with the type of the break
+/// opportunity that ended the line. The ranges cover the whole string, including trailing
+/// whitespace and line terminators.
+///
+/// Lines are broken at every [`Mandatory`](BreakOpportunity::Mandatory) break. A text segment
+/// between two break opportunities that is wider than `max_width` on its own is put on a line of
+/// its own and left overfull.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{wrap::wrap_greedy, BreakOpportunity::{Mandatory, Allowed}};
+/// let text = "The quick brown fox";
+/// let lines = wrap_greedy(text, 10, |line| line.chars().count());
+/// assert!(lines.eq([(0..10, Allowed), (10..19, Mandatory)]));
+/// ```
+pub fn wrap_greedy<'a, W: Width + 'a>(
+    s: &'a str,
+    max_width: W,
+    measure: impl Fn(&str) -> W + 'a,
+) -> impl Iterator<Item = (Range<usize>, BreakOpportunity)> + 'a {
+    wrap(s, max_width, measure, Options::default()).map(|(range, end)| match end {
+        LineEnd::Break(opportunity) => (range, opportunity),
+        LineEnd::Emergency => unreachable!("emergency break without break_anywhere"),
+    })
+}
+
+/// Wraps the string as [`wrap_greedy`] does, with the specified options.
+///
+/// Lines are paired with how they end instead of only the type of the break opportunity, as they
+/// may also end at emergency breaks. The widths of the prefix and indentation of each line, which
+/// are not part of the returned ranges, are subtracted from the width available to the line.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::wrap::{wrap, LineEnd::*, Options};
+/// use unicode_linebreak::BreakOpportunity::{Allowed, Mandatory};
+/// let options = Options { break_anywhere: true, ..Options::default() };
+/// let lines = wrap("abcdefg hi", 4, |line| line.chars().count(), options);
+/// assert!(lines.eq([
+///     (0..4, Emergency),
+///     (4..8, Break(Allowed)),
+///     (8..10, Break(Mandatory)),
+/// ]));
+/// ```
+pub fn wrap<'a, W: Width + 'a>(
+    s: &'a str,
+    max_width: W,
+    measure: impl Fn(&str) -> W + 'a,
+    options: Options<'a>,
+) -> impl Iterator<Item = (Range<usize>, LineEnd)> + 'a {
+    let mut breaks = linebreaks(s).peekable();
+    let mut line_start = 0;
+    let mut last_fit = None;
+    from_fn(move || loop {
+        let &(i, opportunity) = breaks.peek()?;
+        let (prefix, indent) = options.line_prefix(line_start);
+        let fits = |line| measure(prefix) + measure(indent) + measure(line) <= max_width;
+        let line = trim_trailing_whitespace(&s[line_start..i]);
+        if fits(line) {
+            breaks.next();
+            if opportunity == BreakOpportunity::Mandatory {
+                last_fit = None;
+            } else {
+                last_fit = Some((i, opportunity));
+                continue;
+            }
+        } else if let Some((j, opportunity)) = last_fit.take() {
+            let start = line_start;
+            line_start = j;
+            return Some((start..j, LineEnd::Break(opportunity)));
+        } else {
+            // Overfull line without earlier break opportunity
+            if options.break_anywhere {
+                // Fit as many grapheme clusters as possible, but at least one, measuring each
+                // cluster separately as widths are additive
+                let mut width = measure(prefix) + measure(indent);
+                let mut end = None;
+                for j in grapheme_boundaries(line).map(|j| line_start + j) {
+                    width = width + measure(&s[end.unwrap_or(line_start)..j]);
+                    if end.is_some() && width > max_width {
+                        break;
+                    }
+                    end = Some(j);
+                }
+                let end = end.unwrap_or(i);
+                if end < line_start + line.len() {
+                    let start = line_start;
+                    line_start = end;
+                    return Some((start..end, LineEnd::Emergency));
+                }
+            }
+            breaks.next();
+        }
+
+        let start = line_start;
+        line_start = i;
+        return Some((start..i, LineEnd::Break(opportunity)));
+    })
+}
+
+/// Truncates the string at the last break opportunity where it still fits within `max_width`
+/// together with the ellipsis.
+///
+/// Returns the string unchanged if it fits as a whole, and otherwise the text to keep, without
+/// trailing whitespace, to which the caller should append the ellipsis. If not even the text
+/// before the first break opportunity fits, it is cut at an extended grapheme cluster boundary
+/// instead. The string is treated as a single line, and `measure` is assumed to never decrease
+/// as text is appended.
+///
+/// Break opportunities are searched for backwards from where the text stops fitting, with
+/// [`split_at_safe`], so that the rest of the string never has to be processed.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::wrap::truncate_at_break;
+/// let measure = |s: &str| s.chars().count();
+/// assert_eq!(truncate_at_break("The quick brown fox", 12, measure, "…"), "The quick");
+/// assert_eq!(truncate_at_break("Supercalifragilistic", 6, measure, "…"), "Super");
+/// assert_eq!(truncate_at_break("The fox", 12, measure, "…"), "The fox");
+/// ```
+pub fn truncate_at_break<'a, W: Width>(
+    s: &'a str,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+    ellipsis: &str,
+) -> &'a str {
+    if measure(trim_trailing_whitespace(s)) <= max_width {
+        return s;
+    }
+    let ellipsis_width = measure(ellipsis);
+    // End of the longest prefix of grapheme clusters that fits
+    let end = grapheme_boundaries(s)
+        .take_while(|&i| measure(&s[..i]) + ellipsis_width <= max_width)
+        .last()
+        .unwrap_or(0);
+    // Whitespace following it may hang past the end
+    let mut limit = s.len() - s[end..].trim_start_matches(is_trailing_whitespace).len();
+
+    let next_char_end = |i: usize| i + s[i..].chars().next().map_or(0, char::len_utf8);
+    // Include the following character to pick up the break before it
+    let mut head = &s[..next_char_end(limit)];
+    loop {
+        let (prev, safe) = split_at_safe(head);
+        let last_fit = linebreaks(safe)
+            .map(|(i, _)| prev.len() + i)
+            .take_while(|&i| i <= limit)
+            .last();
+        if let Some(i) = last_fit {
+            let line = trim_trailing_whitespace(&s[..i]);
+            if !line.is_empty() {
+                return line;
+            }
+            break;
+        }
+        if prev.is_empty() {
+            break;
+        }
+        limit = prev.len();
+        head = &s[..next_char_end(limit)];
+    }
+    trim_trailing_whitespace(&s[..end])
+}
+
+/// The intrinsic widths of a string, as used for sizing boxes to their content.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct IntrinsicWidths<W> {
+    /// The width of the widest text between two break opportunities, i.e. the narrowest the
+    /// string can be wrapped to without overflowing.
+    pub min_content: W,
+    /// The width of the widest line between two mandatory breaks, i.e. the width of the string
+    /// when not wrapped at all.
+    pub max_content: W,
+}
+
+/// Returns the min-content and max-content widths of the string.
+///
+/// Both are computed in a single pass over the break opportunities. As with the CSS
+/// `white-space: pre-wrap` mode, trailing whitespace is allowed to hang and is not measured.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::wrap::{intrinsic_widths, IntrinsicWidths};
+/// let widths = intrinsic_widths("The quick brown\nfox", |line| line.chars().count());
+/// assert_eq!(widths, IntrinsicWidths { min_content: 5, max_content: 15 });
+/// ```
+pub fn intrinsic_widths<W: Width>(s: &str, measure: impl Fn(&str) -> W) -> IntrinsicWidths<W> {
+    let mut widths = IntrinsicWidths {
+        min_content: measure(""),
+        max_content: measure(""),
+    };
+    let (mut segment_start, mut line_start) = (0, 0);
+    for (i, opportunity) in linebreaks(s) {
+        let width = measure(trim_trailing_whitespace(&s[segment_start..i]));
+        if width > widths.min_content {
+            widths.min_content = width;
+        }
+        segment_start = i;
+        if opportunity == BreakOpportunity::Mandatory {
+            let width = measure(trim_trailing_whitespace(&s[line_start..i]));
+            if width > widths.max_content {
+                widths.max_content = width;
+            }
+            line_start = i;
+        }
+    }
+    widths
+}
+
+/// Wraps the string with the specified options and joins the lines with line feeds.
+///
+/// Each line is preceded by its prefix and indentation, and has trailing whitespace and line
+/// terminators removed. Blank lines only consist of the prefix without trailing whitespace.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::wrap::{fill, Options};
+/// let options = Options { initial_indent: "- ", subsequent_indent: "  ", ..Options::default() };
+/// let text = fill("The quick brown fox", 12, |line| line.chars().count(), options);
+/// assert_eq!(text, "- The quick\n  brown fox");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn fill<W: Width>(
+    s: &str,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+    options: Options<'_>,
+) -> String {
+    let mut result = String::with_capacity(s.len());
+    for (range, _) in wrap(s, max_width, measure, options) {
+        if range.start > 0 {
+            result.push('\n');
+        }
+        let line = trim_trailing_whitespace(&s[range.clone()]);
+        let (prefix, indent) = options.line_prefix(range.start);
+        if line.is_empty() {
+            result.push_str(prefix.trim_end());
+        } else {
+            result.push_str(prefix);
+            result.push_str(indent);
+            result.push_str(line);
+        }
+    }
+    result
+}
+
+/// Returns the square of the unused width of a line divided by `max_width`.
+#[cfg(feature = "alloc")]
+fn badness<W: Width>(width: W, max_width: W) -> f64 {
+    let max = max_width.to_f64().max(f64::MIN_POSITIVE);
+    let r = (max - width.to_f64()) / max;
+    r * r
+}
+
+/// Wraps each paragraph separately by choosing the sequence of breaks of least total cost.
+///
+/// `add_line` is called with the cost so far, the width of the line, the break ending it and
+/// whether it is the last line of the paragraph, and returns the new total cost.
+#[cfg(feature = "alloc")]
+fn wrap_min_cost<W: Width, B: Break, C: Copy + PartialOrd>(
+    s: &str,
+    breaks: impl IntoIterator<Item = (usize, B)>,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+    zero: C,
+    add_line: impl Fn(C, W, (usize, B), bool) -> C,
+) -> Vec<(Range<usize>, B)> {
+    let mut lines = Vec::new();
+    // The start of the paragraph has no break type
+    let mut candidates = vec![(0, None)];
+    let mut best = vec![(zero, 0)];
+    for (i, opportunity) in breaks {
+        candidates.push((i, Some(opportunity)));
+        if !opportunity.is_mandatory() {
+            continue;
+        }
+
+        // Candidate breaks until the end of the paragraph are known: Find the cheapest path
+        best.truncate(1);
+        for j in 1..candidates.len() {
+            let is_last = j == candidates.len() - 1;
+            let mut min: Option<(C, usize)> = None;
+            for k in (0..j).rev() {
+                let width = measure(trim_trailing_whitespace(
+                    &s[candidates[k].0..candidates[j].0],
+                ));
+                if k < j - 1 && width > max_width {
+                    break; // Only allow overfull lines with no earlier break opportunity
+                }
+                let (i, opportunity) = candidates[j];
+                let total = add_line(best[k].0, width, (i, opportunity.unwrap()), is_last);
+                if min.map_or(true, |(min, _)| total < min) {
+                    min = Some((total, k));
+                }
+            }
+            best.push(min.unwrap());
+        }
+
+        let start = lines.len();
+        let mut j = candidates.len() - 1;
+        while j > 0 {
+            let k = best[j].1;
+            lines.push((candidates[k].0..candidates[j].0, candidates[j].1.unwrap()));
+            j = k;
+        }
+        lines[start..].reverse();
+        candidates.drain(..candidates.len() - 1);
+    }
+    lines
+}
+
+/// Wraps the string by choosing the set of breaks that minimizes the total cost of all lines.
+///
+/// The cost of a line is the square of its unused width divided by `max_width`, with the
+/// exception of the last line before each [`Mandatory`](BreakOpportunity::Mandatory) break,
+/// which costs nothing unless overfull. Thus an empty line costs 1. The `penalty` function is
+/// called with the byte index and type of every non-mandatory break and its result is added to
+/// the cost of the line ending there, e.g. to discourage breaking after hyphens. Overfull lines
+/// are only chosen when unavoidable, no matter the penalties.
+///
+/// Lines are returned in the same format as by [`wrap_greedy`]. Only as many preceding break
+/// opportunities are considered for each break as fit on a line, giving a running time of
+/// *O*(*n*·*k*), where *n* is the number of break opportunities and *k* the maximum number of
+/// them per line.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{wrap::wrap_optimal_fit, BreakOpportunity::{Mandatory, Allowed}};
+/// let text = "aaa bb cc ddddd";
+/// let lines = wrap_optimal_fit(text, 6, |line| line.chars().count(), |_, _| 0.0);
+/// // Greedy wrapping would instead have given "aaa bb", "cc", "ddddd"
+/// assert_eq!(lines, [(0..4, Allowed), (4..10, Allowed), (10..15, Mandatory)]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn wrap_optimal_fit<W: Width>(
+    s: &str,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+    penalty: impl Fn(usize, BreakOpportunity) -> f64,
+) -> Vec<(Range<usize>, BreakOpportunity)> {
+    wrap_optimal_fit_with_breaks(s, linebreaks(s), max_width, measure, penalty)
+}
+
+/// Wraps the string as [`wrap_optimal_fit`] does, choosing among the specified breaks.
+///
+/// This allows adding break opportunities, e.g. hyphenation points from
+/// [`linebreaks_hyphenated`](crate::hyphenation::linebreaks_hyphenated), or removing them. The
+/// breaks have to be sorted by byte index and end with a mandatory break at the end of the
+/// string, as those returned by [`linebreaks`] do.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{linebreaks, wrap::wrap_optimal_fit_with_breaks};
+/// use unicode_linebreak::BreakOpportunity::{Mandatory, Allowed};
+/// let text = "well-known fact";
+/// // Only break at spaces
+/// let breaks = linebreaks(text).filter(|&(i, _)| i == text.len() || text[..i].ends_with(' '));
+/// let lines = wrap_optimal_fit_with_breaks(text, breaks, 8, |line| line.len(), |_, _| 0.0);
+/// assert_eq!(lines, [(0..11, Allowed), (11..15, Mandatory)]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn wrap_optimal_fit_with_breaks<W: Width, B: Break>(
+    s: &str,
+    breaks: impl IntoIterator<Item = (usize, B)>,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+    penalty: impl Fn(usize, B) -> f64,
+) -> Vec<(Range<usize>, B)> {
+    // Number of overfull lines, which takes precedence over their cost
+    wrap_min_cost(
+        s,
+        breaks,
+        max_width,
+        measure,
+        (0, 0.0),
+        |(overfull, cost), width, (i, opportunity), is_last| {
+            let overfull = overfull + usize::from(width > max_width);
+            if is_last && width <= max_width {
+                (overfull, cost)
+            } else if is_last {
+                (overfull, cost + badness(width, max_width))
+            } else {
+                (
+                    overfull,
+                    cost + badness(width, max_width) + penalty(i, opportunity),
+                )
+            }
+        },
+    )
+}
+
+/// Wraps the string into lines of roughly equal width, as with the CSS `text-wrap: balance`.
+///
+/// Each paragraph is wrapped into the minimum number of lines that [`wrap_greedy`] would use,
+/// with breaks chosen to minimize the sum of squares of the unused widths of all lines, the last
+/// one included. Lines are returned in the same format as by [`wrap_greedy`].
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{wrap::wrap_balanced, BreakOpportunity::{Mandatory, Allowed}};
+/// let text = "Balanced wrapping of a heading";
+/// let lines = wrap_balanced(text, 20, |line| line.chars().count());
+/// // Greedy wrapping would instead have given "Balanced wrapping of", "a heading"
+/// assert_eq!(lines, [(0..18, Allowed), (18..30, Mandatory)]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn wrap_balanced<W: Width>(
+    s: &str,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+) -> Vec<(Range<usize>, BreakOpportunity)> {
+    wrap_min_cost(
+        s,
+        linebreaks(s),
+        max_width,
+        measure,
+        (0, 0, 0.0),
+        |(num_lines, overfull, cost), width, _, _| {
+            let overfull = overfull + usize::from(width > max_width);
+            (num_lines + 1, overfull, cost + badness(width, max_width))
+        },
+    )
+}
+
+/// Wraps the string greedily, but avoids leaving too little on the last line of paragraphs, as
+/// with the CSS `text-wrap: pretty`.
+///
+/// The last line of each paragraph, i.e. the one ending at a
+/// [`Mandatory`](BreakOpportunity::Mandatory) break, should hold at least `min_words` segments
+/// between break opportunities, or have a width of at least `min_fraction` of `max_width`. If it
+/// does not, it is made to start at the latest earlier break opportunity on the preceding line
+/// that satisfies either condition without making it overfull. If there is none, the greedy
+/// result is kept.
+///
+/// Lines are returned in the same format as by [`wrap_greedy`].
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::{wrap::wrap_pretty, BreakOpportunity::{Mandatory, Allowed}};
+/// let text = "The quick brown fox jumps";
+/// let lines = wrap_pretty(text, 20, |line| line.chars().count(), 2, 0.5);
+/// // Greedy wrapping would instead have given "The quick brown fox", "jumps"
+/// assert_eq!(lines, [(0..16, Allowed), (16..25, Mandatory)]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn wrap_pretty<W: Width>(
+    s: &str,
+    max_width: W,
+    measure: impl Fn(&str) -> W,
+    min_words: usize,
+    min_fraction: f64,
+) -> Vec<(Range<usize>, BreakOpportunity)> {
+    let breaks: Vec<_> = linebreaks(s).collect();
+    let mut lines: Vec<_> = wrap_greedy(s, max_width, &measure).collect();
+    // Returns the index of the first break opportunity at or after the byte index
+    let break_idx = |i: usize| breaks.partition_point(|&(j, _)| j < i);
+
+    for m in 1..lines.len() {
+        let (ref last, opportunity) = lines[m];
+        if opportunity != BreakOpportunity::Mandatory
+            || lines[m - 1].1 == BreakOpportunity::Mandatory
+        {
+            continue; // Not the last line of a paragraph with multiple lines
+        }
+        let end_idx = break_idx(last.end);
+        let is_satisfied = |start: usize, width: W| {
+            end_idx - break_idx(start + 1) + 1 >= min_words
+                || width.to_f64() >= min_fraction * max_width.to_f64()
+        };
+        let width = measure(trim_trailing_whitespace(&s[last.clone()]));
+        if is_satisfied(last.start, width) {
+            continue;
+        }
+
+        let prev_start = lines[m - 1].0.start;
+        for &(i, opportunity) in breaks[break_idx(prev_start + 1)..break_idx(last.start)]
+            .iter()
+            .rev()
+        {
+            let width = measure(trim_trailing_whitespace(&s[i..last.end]));
+            if width > max_width {
+                break;
+            }
+            if is_satisfied(i, width) {
+                lines[m - 1] = (prev_start..i, opportunity);
+                lines[m].0.start = i;
+                break;
+            }
+        }
+    }
+    lines
+}
+
+/// Returns whether the character may be part of the prefix of a comment or quoted email.
+#[cfg(feature = "alloc")]
+fn is_prefix_char(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '/' | '#' | '>' | '!' | ';' | '%')
+}
+
+/// Returns the length of the list marker and its following space at the start of the line.
+#[cfg(feature = "alloc")]
+fn list_marker_len(line: &str) -> Option<usize> {
+    let len = if line.starts_with(&['-', '*', '+'][..]) {
+        1
+    } else {
+        let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+        match line.as_bytes().get(digits) {
+            Some(b'.' | b')') if digits > 0 => digits + 1,
+            _ => return None,
+        }
+    };
+    if line[len..].starts_with(' ') {
+        Some(len + 1)
+    } else {
+        None
+    }
+}
+
+/// Reflows text with existing line breaks, such as a source code comment or a quoted email.
+///
+/// The prefix of each line, made up of whitespace and the characters `/#>!;%`, is removed. Then
+/// single line breaks are replaced by spaces, while blank lines, lines starting with a list marker
+/// (`-`, `*`, `+`, `1.` or `1)`) and lines whose prefix differs from that of the previous line,
+/// apart from trailing whitespace, start new paragraphs. A deeper quoted reply thus stays separate
+/// from the text around it. Each paragraph is wrapped as by [`fill`], with the prefix of its first
+/// line put back and list items given a hanging indent.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use unicode_linebreak::wrap::reflow;
+/// let comment = "/// The quick brown\n/// fox jumps over the\n/// lazy dog.\n///\n\
+///                /// - An item\n///   continued\n";
+/// assert_eq!(
+///     reflow(comment, 24, |line| line.chars().count()),
+///     "/// The quick brown fox\n/// jumps over the lazy\n/// dog.\n///\n/// - An item continued\n"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn reflow<W: Width>(s: &str, max_width: W, measure: impl Fn(&str) -> W) -> String {
+    let mut lines = Vec::new();
+    let mut paragraph = String::new();
+    let mut prefix = "";
+    let mut hanging_indent = 0;
+    let flush = |lines: &mut Vec<String>, paragraph: &mut String, prefix, hanging_indent| {
+        if !paragraph.is_empty() {
+            let subsequent_indent = " ".repeat(hanging_indent);
+            let options = Options {
+                subsequent_indent: &subsequent_indent,
+                prefix,
+                ..Options::default()
+            };
+            lines.push(fill(paragraph, max_width, &measure, options));
+            paragraph.clear();
+        }
+    };
+    for line in s.lines() {
+        let (line_prefix, content) =
+            line.split_at(line.find(|c| !is_prefix_char(c)).unwrap_or(line.len()));
+        if content.is_empty() {
+            flush(&mut lines, &mut paragraph, prefix, hanging_indent);
+            lines.push(line.trim_end().into());
+            continue;
+        }
+
+        let marker_len = list_marker_len(content);
+        if paragraph.is_empty()
+            || marker_len.is_some()
+            || line_prefix.trim_end() != prefix.trim_end()
+        {
+            flush(&mut lines, &mut paragraph, prefix, hanging_indent);
+            prefix = line_prefix;
+            paragraph.push_str(content.trim_end());
+            hanging_indent = marker_len.unwrap_or(0);
+        } else {
+            paragraph.push(' ');
+            paragraph.push_str(content.trim_end());
+        }
+    }
+    flush(&mut lines, &mut paragraph, prefix, hanging_indent);
+
+    let mut result = lines.join("\n");
+    if s.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BreakOpportunity::{Allowed, Mandatory};
+
+    #[test]
+    fn greedy_overfull() {
+        let lines = wrap_greedy("a verylongword b\nc", 4, |line| line.len());
+        assert!(lines.eq([
+            (0..2, Allowed),
+            (2..15, Allowed),
+            (15..17, Mandatory),
+            (17..18, Mandatory)
+        ]));
+    }
+
+    #[test]
+    fn greedy_trailing_whitespace() {
+        let lines = wrap_greedy("ab    cd", 2, |line| line.len());
+        assert!(lines.eq([(0..6, Allowed), (6..8, Mandatory)]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn optimal_fit_penalty() {
+        let text = "aaaa bb-cc dd";
+        let lines = wrap_optimal_fit(text, 8, |line| line.len(), |_, _| 0.0);
+        assert_eq!(lines, [(0..8, Allowed), (8..13, Mandatory)]);
+        let lines = wrap_optimal_fit(
+            text,
+            8,
+            |line| line.len(),
+            |i, _| {
+                if text[..i].ends_with('-') {
+                    1.0
+                } else {
+                    0.0
+                }
+            },
+        );
+        assert_eq!(lines, [(0..5, Allowed), (5..13, Mandatory)]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn optimal_fit_paragraphs() {
+        let lines = wrap_optimal_fit("a b\n\nc", 1, |line| line.len(), |_, _| 0.0);
+        assert_eq!(
+            lines,
+            [
+                (0..2, Allowed),
+                (2..4, Mandatory),
+                (4..5, Mandatory),
+                (5..6, Mandatory)
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn balanced_short_last_line() {
+        let lines = wrap_balanced("aaa bbb c", 7, |line| line.len());
+        assert_eq!(lines, [(0..4, Allowed), (4..9, Mandatory)]);
+        // Never uses more lines than greedy wrapping
+        let lines = wrap_balanced("aaaa bb cc dd eeee", 5, |line| line.len());
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn pretty_fallback() {
+        // Moving "bbbbb" down would make the last line overfull
+        let lines = wrap_pretty("aaa bbbbb ccc", 8, |line| line.len(), 2, 0.5);
+        assert_eq!(
+            lines,
+            [(0..4, Allowed), (4..10, Allowed), (10..13, Mandatory)]
+        );
+    }
+
+    #[test]
+    fn break_anywhere() {
+        let options = Options {
+            break_anywhere: true,
+            ..Options::default()
+        };
+        let lines = wrap("a verylongword b", 4, |line| line.len(), options);
+        assert!(lines.eq([
+            (0..2, LineEnd::Break(Allowed)),
+            (2..6, LineEnd::Emergency),
+            (6..10, LineEnd::Emergency),
+            (10..15, LineEnd::Break(Allowed)),
+            (15..16, LineEnd::Break(Mandatory))
+        ]));
+        // Keeps combining marks with their base
+        let lines = wrap("e\u{301}e\u{301}", 1, |line| line.chars().count(), options);
+        assert!(lines.eq([
+            (0..3, LineEnd::Emergency),
+            (3..6, LineEnd::Break(Mandatory))
+        ]));
+        let lines = wrap("กิกิกิ", 1, |line| line.chars().count(), options);
+        assert!(lines.eq([
+            (0..6, LineEnd::Emergency),
+            (6..12, LineEnd::Emergency),
+            (12..18, LineEnd::Break(Mandatory))
+        ]));
+    }
+
+    #[test]
+    fn truncate_hanging_whitespace() {
+        let measure = |line: &str| line.len();
+        // The space after "aaa" does not need to fit
+        assert_eq!(truncate_at_break("aaa bbb", 5, measure, ".."), "aaa");
+        assert_eq!(truncate_at_break("aaa bbb", 4, measure, ".."), "aa");
+        assert_eq!(truncate_at_break("  aaaaaa", 6, measure, ".."), "  aa");
+        assert_eq!(truncate_at_break("aaa", 1, measure, ".."), "");
+        // Does not break within the em dashes
+        let s = "aaa bbb — — ccc";
+        assert_eq!(truncate_at_break(s, 16, measure, ".."), "aaa bbb");
+        assert_eq!(truncate_at_break(s, 17, measure, ".."), "aaa bbb — —");
+    }
+
+    #[test]
+    fn intrinsic_widths_trailing_whitespace() {
+        let widths = intrinsic_widths("ab   cde  \n  f \r\n", |line| line.len());
+        assert_eq!(widths.min_content, 3);
+        assert_eq!(widths.max_content, 8);
+        let widths = intrinsic_widths("", |line| line.len());
+        assert_eq!(widths.min_content, 0);
+        assert_eq!(widths.max_content, 0);
+    }
+
+    #[test]
+    fn grapheme_clusters() {
+        assert!(grapheme_boundaries("a\r\n\u{301}").eq([1, 3, 5]));
+        assert!(grapheme_boundaries("🇸🇪🇫🇮🇸").eq([8, 16, 20]));
+        assert!(grapheme_boundaries("\u{1100}\u{1161}\u{11A8}가").eq([9, 12]));
+        assert!(grapheme_boundaries("👩\u{200D}🔬👋🏽").eq([11, 19]));
+        // Controls are always separate, while Thai vowel signs are kept with their base
+        assert!(grapheme_boundaries("ab\u{1}cd").eq(1..=5));
+        assert!(grapheme_boundaries("กิกิ").eq([6, 12]));
+        assert!(grapheme_boundaries("a\u{200D}👩").eq([4, 8]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn fill_prefix() {
+        let options = Options {
+            prefix: "// ",
+            ..Options::default()
+        };
+        let text = fill("aaa bbb ccc\n\nddd", 10, |line| line.len(), options);
+        assert_eq!(text, "// aaa bbb\n// ccc\n//\n// ddd");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn reflow_quoted_list() {
+        let text = "> 1. First item\n>    on two lines\n> 2. Second\n>\n> Paragraph\n> here";
+        assert_eq!(
+            reflow(text, 14, |line| line.len()),
+            "> 1. First\n>    item on\n>    two lines\n> 2. Second\n>\n> Paragraph\n> here"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn reflow_nested_quote() {
+        let text = "> a\n> > b c\n> > d\n> e\n>\n> > f";
+        assert_eq!(
+            reflow(text, 10, |line| line.len()),
+            "> a\n> > b c d\n> e\n>\n> > f"
+        );
+        assert_eq!(
+            reflow(text, 7, |line| line.len()),
+            "> a\n> > b c\n> > d\n> e\n>\n> > f"
+        );
+    }
+}
@@ -4,7 +4,6 @@ use std::char;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
 use std::iter::from_fn;
-use std::u32;
 use unicode_linebreak::*;
 
 const TEST_FILE: &str = "tests/LineBreakTest.txt";